@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE users DROP COLUMN IF EXISTS totp_secret;
+ALTER TABLE users DROP COLUMN IF EXISTS two_fa_method;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS two_fa_method TEXT NOT NULL DEFAULT 'email';
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret TEXT;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS webauthn_credentials(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   public_key BYTEA NOT NULL,
+   sign_count BIGINT NOT NULL DEFAULT 0,
+   created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS webauthn_credentials_email_idx ON webauthn_credentials(email);
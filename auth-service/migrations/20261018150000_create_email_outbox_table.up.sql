@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS email_outbox(
+   id TEXT NOT NULL PRIMARY KEY,
+   recipient TEXT NOT NULL,
+   subject TEXT NOT NULL,
+   html_body TEXT NOT NULL,
+   text_body TEXT NOT NULL,
+   status TEXT NOT NULL DEFAULT 'pending',
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at BIGINT NOT NULL,
+   last_error TEXT,
+   created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS email_outbox_due_idx ON email_outbox(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS email_outbox_recipient_idx ON email_outbox(recipient);
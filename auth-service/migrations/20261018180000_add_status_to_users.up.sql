@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Accounts created before verification was introduced are treated as verified
+ALTER TABLE users ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active';
+ALTER TABLE users ALTER COLUMN status SET DEFAULT 'pending-verification';
@@ -0,0 +1,11 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS audit_events(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   event_type TEXT NOT NULL,
+   ip TEXT,
+   user_agent TEXT,
+   created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_email_idx ON audit_events(email);
@@ -1,12 +1,26 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::domain::{
+    AuditLogStore, BannedTokenStore, CredentialStore, EmailClient, EmailOutboxStore, EmailVerificationTokenStore, LoginAttemptStore,
+    PasswordResetTokenStore, RateLimitStore, RefreshTokenStore, SessionStore, TwoFACodeStore, UserStore,
+    WebAuthnChallengeStore,
+};
 
 // Using a type alias to improve readability!
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+pub type EmailVerificationTokenStoreType = Arc<RwLock<dyn EmailVerificationTokenStore + Send + Sync>>;
+pub type CredentialStoreType = Arc<RwLock<dyn CredentialStore + Send + Sync>>;
+pub type WebAuthnChallengeStoreType = Arc<RwLock<dyn WebAuthnChallengeStore + Send + Sync>>;
+pub type LoginAttemptStoreType = Arc<RwLock<dyn LoginAttemptStore + Send + Sync>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
+pub type EmailOutboxStoreType = Arc<RwLock<dyn EmailOutboxStore + Send + Sync>>;
+pub type AuditLogStoreType = Arc<RwLock<dyn AuditLogStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
 
 #[derive(Clone)]
@@ -14,16 +28,37 @@ pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub email_client: EmailClientType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_store: SessionStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub credential_store: CredentialStoreType,
+    pub webauthn_challenge_store: WebAuthnChallengeStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
+    pub rate_limit_store: RateLimitStoreType,
+    pub email_outbox: EmailOutboxStoreType,
+    pub audit_log_store: AuditLogStoreType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
-        email_client: EmailClientType,
+        refresh_token_store: RefreshTokenStoreType,
+        session_store: SessionStoreType,
+        password_reset_token_store: PasswordResetTokenStoreType,
+        email_verification_token_store: EmailVerificationTokenStoreType,
+        credential_store: CredentialStoreType,
+        webauthn_challenge_store: WebAuthnChallengeStoreType,
+        login_attempt_store: LoginAttemptStoreType,
+        rate_limit_store: RateLimitStoreType,
+        email_outbox: EmailOutboxStoreType,
+        audit_log_store: AuditLogStoreType,
     ) -> Self {
-        Self { user_store, banned_token_store, two_fa_code_store, email_client, }
+        Self { user_store, banned_token_store, two_fa_code_store, refresh_token_store, session_store,
+            password_reset_token_store, email_verification_token_store, credential_store, webauthn_challenge_store,
+            login_attempt_store, rate_limit_store, email_outbox, audit_log_store, }
     }
 }
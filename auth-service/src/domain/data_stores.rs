@@ -1,9 +1,10 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use rand::distributions::Alphanumeric;
 use rand::Rng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use super::{email::Email, password::Password, User};
+use super::{email::Email, password::Password, AccountStatus, EmailMessage, RateLimit, RateLimitStatus, RecoveryCode, TwoFAMethod, User};
 
 #[async_trait::async_trait]
 pub trait UserStore {
@@ -13,6 +14,37 @@ pub trait UserStore {
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError>;
+    async fn increment_token_version(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Also clears `password_reset_required`
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError>;
+    // Activates the account if it was pending verification, other statuses are kept
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Secret of a TOTP enrollment, replacing any enrollment that wasn't confirmed
+    async fn set_totp_secret(&mut self, email: &Email, secret: String) -> Result<(), UserStoreError>;
+    // Turns 2FA on with the given method
+    async fn set_two_fa_method(&mut self, email: &Email, method: TwoFAMethod) -> Result<(), UserStoreError>;
+    // Replaces all of the user's recovery codes
+    async fn set_recovery_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), UserStoreError>;
+    // Removes the code if it belongs to the user, so it can only be used once.
+    // Fails with `InvalidCredentials` otherwise.
+    async fn use_recovery_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), UserStoreError>;
+    // Replaces the roles granted to the user
+    async fn set_roles(&mut self, email: &Email, roles: Vec<String>) -> Result<(), UserStoreError>;
+    // Users ordered by email, only the ones whose email contains `email_search` (ignoring case) if given
+    async fn list_users(&self, email_search: Option<&str>, offset: usize, limit: usize)
+        -> Result<UserPage, UserStoreError>;
+    async fn set_status(&mut self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError>;
+    async fn set_password_reset_required(&mut self, email: &Email, required: bool) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError>;
+    // Removes the user along with their recovery codes
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    // Number of users matching the search across all pages
+    pub total: usize,
 }
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
@@ -45,15 +77,24 @@ pub trait TwoFACodeStore {
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    // Fails with `TooManyAttempts` once the code has been invalidated by failed attempts
     async fn get_code(
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    // Counts a wrong code for the current login attempt.
+    // Fails with `TooManyAttempts` when this attempt used up the last one.
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    // Swaps in a new code for the current login attempt and restarts its TTL. Failed attempts still count.
+    // Fails with `TooManyResends` once the code of the attempt has been replaced too often.
+    async fn resend_code(&mut self, email: &Email, code: TwoFACode) -> Result<(), TwoFACodeStoreError>;
 }
 
 #[derive(Debug, PartialEq)]
 pub enum TwoFACodeStoreError {
     LoginAttemptIdNotFound,
+    TooManyAttempts,
+    TooManyResends,
     UnexpectedError,
 }
 
@@ -118,3 +159,445 @@ impl AsRef<str> for TwoFACode {
         &self.0
     }
 }
+
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RefreshTokenStoreError {
+    TokenNotFound,
+    TokenReused(RefreshTokenRecord),
+    FamilyRevoked,
+    UnexpectedError,
+}
+
+// Every refresh token belongs to a family started by a single login.
+// Rotating a token keeps the family, so a replayed token can revoke the whole chain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RefreshTokenRecord {
+    pub email: String,
+    pub family_id: String,
+    pub token_version: i32,
+    pub used: bool,
+}
+
+impl RefreshTokenRecord {
+    pub fn new(user: &User, family_id: String) -> Self {
+        Self {
+            email: user.email.as_ref().to_owned(),
+            family_id,
+            token_version: user.token_version,
+            used: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct RefreshToken(String);
+
+impl RefreshToken {
+    pub fn parse(token: String) -> Result<Self, String> {
+        match token.len() == REFRESH_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            true => Ok(RefreshToken(token)),
+            false => Err("Invalid refresh token".to_string()),
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        let token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(REFRESH_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        RefreshToken(token)
+    }
+}
+
+impl AsRef<str> for RefreshToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError>;
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn remove_session(&mut self, id: &str) -> Result<(), SessionStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SessionStoreError {
+    SessionNotFound,
+    UnexpectedError,
+}
+
+// A session is recorded for every issued JWT auth token and identified by its `jti` claim
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub email: String,
+    pub created_at: i64,
+    // When the session ends, which for sessions with a refresh token family is when the refresh token expires
+    pub expires_at: i64,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub refresh_family_id: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        token: PasswordResetToken,
+        email: Email,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    // Returns the email the token was issued for and removes the token, so it can only be used once
+    async fn consume_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PasswordResetTokenStoreError {
+    TokenNotFound,
+    UnexpectedError,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PasswordResetToken(String);
+
+impl PasswordResetToken {
+    pub fn parse(token: String) -> Result<Self, String> {
+        match token.len() == PASSWORD_RESET_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            true => Ok(PasswordResetToken(token)),
+            false => Err("Invalid password reset token".to_string()),
+        }
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        let token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(PASSWORD_RESET_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        PasswordResetToken(token)
+    }
+}
+
+impl AsRef<str> for PasswordResetToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+const PASSWORD_RESET_TOKEN_LENGTH: usize = 64;
+
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore {
+    // Fails with `TooManyRequests` if a token was issued for the same email too recently
+    async fn add_token(
+        &mut self,
+        token: EmailVerificationToken,
+        email: Email,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+    // Returns the email the token was issued for and removes the token, so it can only be used once
+    async fn consume_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EmailVerificationTokenStoreError {
+    TokenNotFound,
+    TooManyRequests,
+    UnexpectedError,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EmailVerificationToken(String);
+
+impl EmailVerificationToken {
+    pub fn parse(token: String) -> Result<Self, String> {
+        match token.len() == EMAIL_VERIFICATION_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            true => Ok(EmailVerificationToken(token)),
+            false => Err("Invalid email verification token".to_string()),
+        }
+    }
+}
+
+impl Default for EmailVerificationToken {
+    fn default() -> Self {
+        let token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(EMAIL_VERIFICATION_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        EmailVerificationToken(token)
+    }
+}
+
+impl AsRef<str> for EmailVerificationToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+const EMAIL_VERIFICATION_TOKEN_LENGTH: usize = 64;
+
+#[async_trait::async_trait]
+pub trait CredentialStore {
+    async fn add_credential(&mut self, credential: WebAuthnCredential) -> Result<(), CredentialStoreError>;
+    async fn get_credential(&self, id: &str) -> Result<WebAuthnCredential, CredentialStoreError>;
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, CredentialStoreError>;
+    // Stores the signature counter reported by the latest successful login
+    async fn update_sign_count(&mut self, id: &str, sign_count: u32) -> Result<(), CredentialStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CredentialStoreError {
+    CredentialAlreadyExists,
+    CredentialNotFound,
+    UnexpectedError,
+}
+
+// Passkey registered by an authenticator, identified by its base64url encoded credential id
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebAuthnCredential {
+    pub id: String,
+    pub email: String,
+    // COSE_Key the authenticator's signatures are verified with
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+    pub created_at: i64,
+}
+
+#[async_trait::async_trait]
+pub trait WebAuthnChallengeStore {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge) -> Result<(), WebAuthnChallengeStoreError>;
+    // Returns the ceremony the challenge was issued for and removes it, so it can only be answered once
+    async fn consume_challenge(
+        &mut self,
+        challenge: &str,
+    ) -> Result<WebAuthnChallenge, WebAuthnChallengeStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WebAuthnChallengeStoreError {
+    ChallengeNotFound,
+    UnexpectedError,
+}
+
+// Challenge handed out by a begin endpoint. The authenticator signs it, so the finish endpoint
+// can find the ceremony it belongs to from the client data alone.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WebAuthnChallenge {
+    pub challenge: String,
+    pub ceremony: WebAuthnCeremony,
+    // User registering a credential, or the user logging in if they gave their email
+    pub email: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WebAuthnCeremony {
+    Registration,
+    Authentication,
+}
+
+#[async_trait::async_trait]
+pub trait LoginAttemptStore {
+    // Seconds left until the lockout ends, `None` if the key isn't locked
+    async fn get_lockout(&self, key: &LoginAttemptKey) -> Result<Option<i64>, LoginAttemptStoreError>;
+    // Counts a failed login, locking the key once it has failed too often in a row
+    async fn record_failure(&mut self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError>;
+    async fn reset(&mut self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LoginAttemptStoreError {
+    UnexpectedError,
+}
+
+// What failed logins are counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LoginAttemptKey {
+    Email(Email),
+    Ip(String),
+}
+
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    // Takes a token from the bucket stored under the key, creating a full one for keys seen for the first time
+    async fn take_token(&mut self, key: &str, limit: &RateLimit) -> Result<RateLimitStatus, RateLimitStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RateLimitStoreError {
+    UnexpectedError,
+}
+
+// Emails waiting to be delivered by the outbox worker, so requests don't depend on the email provider being up
+#[async_trait::async_trait]
+pub trait EmailOutboxStore {
+    // Queueing an email with an id that is already taken keeps the first one
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError>;
+    // Hands out up to `limit` pending emails due at `now`, counting an attempt for each. They aren't handed out
+    // again before `lease_until`, so a worker that dies while sending only delays the email.
+    async fn claim_due(&mut self, now: i64, lease_until: i64, limit: usize)
+        -> Result<Vec<OutboxEmail>, EmailOutboxStoreError>;
+    async fn mark_sent(&mut self, id: &str) -> Result<(), EmailOutboxStoreError>;
+    // Schedules another attempt, or moves the email to the dead letters if `retry_at` is `None`
+    async fn mark_failed(&mut self, id: &str, error: &str, retry_at: Option<i64>) -> Result<(), EmailOutboxStoreError>;
+    async fn get_emails(&self, recipient: &Email) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError>;
+    // Removes every email to the recipient, whether or not it was delivered
+    async fn delete_emails(&mut self, recipient: &Email) -> Result<(), EmailOutboxStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EmailOutboxStoreError {
+    EmailNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEmail {
+    pub id: String,
+    pub recipient: String,
+    pub message: EmailMessage,
+    pub status: OutboxEmailStatus,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+impl OutboxEmail {
+    pub fn new(recipient: &Email, message: EmailMessage, now: i64) -> Self {
+        OutboxEmail {
+            id: Uuid::new_v4().to_string(),
+            recipient: recipient.as_ref().to_owned(),
+            message,
+            status: OutboxEmailStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutboxEmailStatus {
+    Pending,
+    Sent,
+    // Gave up on, either rejected by the provider or out of attempts
+    Dead,
+}
+
+impl OutboxEmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxEmailStatus::Pending => "pending",
+            OutboxEmailStatus::Sent => "sent",
+            OutboxEmailStatus::Dead => "dead",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "pending" => Ok(OutboxEmailStatus::Pending),
+            "sent" => Ok(OutboxEmailStatus::Sent),
+            "dead" => Ok(OutboxEmailStatus::Dead),
+            _ => Err(format!("Unknown outbox email status: {}", value)),
+        }
+    }
+}
+
+// Security relevant events on an account, kept so users can see what happened to it
+#[async_trait::async_trait]
+pub trait AuditLogStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError>;
+    // Oldest first
+    async fn get_events(&self, email: &Email) -> Result<Vec<AuditEvent>, AuditLogStoreError>;
+    async fn delete_events(&mut self, email: &Email) -> Result<(), AuditLogStoreError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuditLogStoreError {
+    UnexpectedError,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub id: String,
+    pub email: String,
+    pub event_type: AuditEventType,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: i64,
+}
+
+impl AuditEvent {
+    pub fn new(email: &Email, event_type: AuditEventType, ip: Option<String>, user_agent: Option<String>, now: i64) -> Self {
+        AuditEvent {
+            id: Uuid::new_v4().to_string(),
+            email: email.as_ref().to_owned(),
+            event_type,
+            ip,
+            user_agent,
+            created_at: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditEventType {
+    Login,
+    PasswordChanged,
+    PasswordReset,
+    SessionRevoked,
+    LoggedOutEverywhere,
+}
+
+impl AuditEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEventType::Login => "login",
+            AuditEventType::PasswordChanged => "password_changed",
+            AuditEventType::PasswordReset => "password_reset",
+            AuditEventType::SessionRevoked => "session_revoked",
+            AuditEventType::LoggedOutEverywhere => "logged_out_everywhere",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "login" => Ok(AuditEventType::Login),
+            "password_changed" => Ok(AuditEventType::PasswordChanged),
+            "password_reset" => Ok(AuditEventType::PasswordReset),
+            "session_revoked" => Ok(AuditEventType::SessionRevoked),
+            "logged_out_everywhere" => Ok(AuditEventType::LoggedOutEverywhere),
+            _ => Err(format!("Unknown audit event type: {}", value)),
+        }
+    }
+}
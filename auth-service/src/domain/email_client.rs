@@ -6,7 +6,30 @@ pub trait EmailClient {
     async fn send_email(
         &self,
         recipient: &Email,
-        subject: &str,
-        content: &str,
-    ) -> Result<(), String>;
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError>;
+}
+
+// An email with both an HTML and a plain text body, recipients' mail clients pick the one they can show
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmailClientError {
+    // The message couldn't be built, e.g. because of an invalid address
+    InvalidMessage(String),
+    // The provider refused the message, sending it again won't help
+    Rejected(String),
+    // Timeouts, connection problems and temporary failures, sending it again later may work
+    Unavailable(String),
+}
+
+impl EmailClientError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, EmailClientError::Unavailable(_))
+    }
 }
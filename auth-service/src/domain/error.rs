@@ -5,4 +5,20 @@ pub enum AuthAPIError {
     UnexpectedError,
     MissingToken,
     InvalidToken,
+    SessionNotFound,
+    InvalidClientCredentials,
+    EmailNotVerified,
+    TooManyRequests,
+    TotpAlreadyEnabled,
+    CredentialAlreadyExists,
+    TwoFANotEnabled,
+    // Authenticated, but missing the role the route requires
+    Forbidden,
+    UserNotFound,
+    AccountSuspended,
+    AccountDeleted,
+    PasswordResetRequired,
+    TooManyTwoFAAttempts,
+    // Seconds until the client may try again
+    TooManyLoginAttempts(i64),
 }
@@ -3,6 +3,8 @@ mod error;
 pub(crate) mod data_stores;
 pub(crate) mod email;
 mod password;
+mod rate_limit;
+mod recovery_code;
 pub mod email_client;
 
 pub use user::*;
@@ -10,4 +12,6 @@ pub use error::*;
 pub use data_stores::*;
 pub use email::*;
 pub use password::*;
+pub use rate_limit::*;
+pub use recovery_code::*;
 pub use email_client::*;
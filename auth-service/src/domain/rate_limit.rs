@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+// Number of requests allowed per period. The bucket holds `capacity` tokens and refills completely over the period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period_seconds: u32,
+}
+
+impl RateLimit {
+    // Parses the `capacity/period_seconds` format used in the configuration, e.g. `10/60`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (capacity, period_seconds) = value
+            .trim()
+            .split_once('/')
+            .ok_or("Rate limit must be formatted as capacity/period_seconds".to_string())?;
+
+        let capacity: u32 = capacity.trim().parse().map_err(|_| "Invalid rate limit capacity".to_string())?;
+        let period_seconds: u32 = period_seconds.trim().parse().map_err(|_| "Invalid rate limit period".to_string())?;
+
+        match capacity > 0 && period_seconds > 0 {
+            true => Ok(RateLimit { capacity, period_seconds }),
+            false => Err("Rate limit capacity and period must be positive".to_string()),
+        }
+    }
+
+    // Tokens added to the bucket every millisecond
+    fn refill_rate(&self) -> f64 {
+        self.capacity as f64 / (self.period_seconds as f64 * 1000.0)
+    }
+}
+
+// Limit applied to every route, unless the route has its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimits {
+    pub default: RateLimit,
+    pub routes: HashMap<String, RateLimit>,
+}
+
+impl RateLimits {
+    pub fn for_route(&self, route: &str) -> &RateLimit {
+        self.routes.get(route).unwrap_or(&self.default)
+    }
+}
+
+// State of a bucket after a request took, or tried to take, a token from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub remaining: u32,
+    // Seconds until the bucket is full again
+    pub reset_seconds: u64,
+    // Seconds until the next request is allowed, 0 if it already is
+    pub retry_after_seconds: u64,
+}
+
+impl RateLimitStatus {
+    pub fn new(limit: &RateLimit, tokens: f64, allowed: bool) -> Self {
+        let rate = limit.refill_rate();
+
+        RateLimitStatus {
+            allowed,
+            remaining: tokens.floor() as u32,
+            reset_seconds: ((limit.capacity as f64 - tokens) / rate / 1000.0).ceil() as u64,
+            retry_after_seconds: ((1.0 - tokens).max(0.0) / rate / 1000.0).ceil() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub updated_at_ms: i64,
+}
+
+impl TokenBucket {
+    pub fn new(limit: &RateLimit, now_ms: i64) -> Self {
+        TokenBucket { tokens: limit.capacity as f64, updated_at_ms: now_ms }
+    }
+
+    // Refills the bucket for the time passed since the last request, then takes a token if there is one
+    pub fn take(&mut self, limit: &RateLimit, now_ms: i64) -> RateLimitStatus {
+        let elapsed_ms = (now_ms - self.updated_at_ms).max(0) as f64;
+
+        self.tokens = (self.tokens + elapsed_ms * limit.refill_rate()).min(limit.capacity as f64);
+        self.updated_at_ms = now_ms;
+
+        let allowed = self.tokens >= 1.0;
+
+        if allowed {
+            self.tokens -= 1.0;
+        }
+
+        RateLimitStatus::new(limit, self.tokens, allowed)
+    }
+
+    // Time the bucket is full again, from then on it's no different from a new one
+    pub fn full_at_ms(&self, limit: &RateLimit) -> i64 {
+        self.updated_at_ms + ((limit.capacity as f64 - self.tokens) / limit.refill_rate()).ceil() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit { capacity: 2, period_seconds: 10 };
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(RateLimit::parse(" 10/60 "), Ok(RateLimit { capacity: 10, period_seconds: 60 }));
+        assert!(RateLimit::parse("10").is_err());
+        assert!(RateLimit::parse("0/60").is_err());
+        assert!(RateLimit::parse("10/x").is_err());
+    }
+
+    #[test]
+    fn test_take_until_empty() {
+        let mut bucket = TokenBucket::new(&LIMIT, 0);
+
+        let status = bucket.take(&LIMIT, 0);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+        assert_eq!(status.reset_seconds, 5);
+
+        assert!(bucket.take(&LIMIT, 0).allowed);
+
+        let status = bucket.take(&LIMIT, 0);
+        assert!(!status.allowed);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.retry_after_seconds, 5);
+        assert_eq!(status.reset_seconds, 10);
+    }
+
+    #[test]
+    fn test_take_refills_over_time() {
+        let mut bucket = TokenBucket::new(&LIMIT, 0);
+
+        bucket.take(&LIMIT, 0);
+        bucket.take(&LIMIT, 0);
+
+        // One token every 5 seconds
+        assert!(!bucket.take(&LIMIT, 4_000).allowed);
+        assert!(bucket.take(&LIMIT, 5_000).allowed);
+
+        // Never more than the capacity
+        let status = bucket.take(&LIMIT, 60_000);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+        assert_eq!(bucket.full_at_ms(&LIMIT), 65_000);
+    }
+}
@@ -0,0 +1,66 @@
+use rand::Rng;
+
+// Number of codes handed out whenever recovery codes are (re)generated
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const RECOVERY_CODE_LENGTH: usize = 10;
+const RECOVERY_CODE_CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+// Single-use code letting a user with 2FA log in without their second factor, formatted as `xxxxx-xxxxx`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RecoveryCode(String);
+
+impl RecoveryCode {
+    // Codes are typed in by hand, so the case and the separator don't matter
+    pub fn parse(code: &str) -> Result<Self, String> {
+        let code: String = code
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        match code.len() == RECOVERY_CODE_LENGTH && code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            true => Ok(RecoveryCode(format!("{}-{}", &code[..5], &code[5..]))),
+            false => Err("Invalid recovery code".to_string()),
+        }
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let code: String = (0..RECOVERY_CODE_LENGTH)
+            .map(|_| RECOVERY_CODE_CHARSET[rng.gen_range(0..RECOVERY_CODE_CHARSET.len())] as char)
+            .collect();
+        RecoveryCode(format!("{}-{}", &code[..5], &code[5..]))
+    }
+}
+
+impl AsRef<str> for RecoveryCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_normalizes_code() {
+        assert_eq!(RecoveryCode::parse("ABCDE-12345").unwrap().as_ref(), "abcde-12345");
+        assert_eq!(RecoveryCode::parse(" abcde12345 ").unwrap().as_ref(), "abcde-12345");
+    }
+
+    #[test]
+    fn test_parse_with_incorrect_value() {
+        assert!(RecoveryCode::parse("123456").is_err());
+        assert!(RecoveryCode::parse("abcde-1234!").is_err());
+    }
+
+    #[test]
+    fn test_default_parses() {
+        let code = RecoveryCode::default();
+        assert_eq!(RecoveryCode::parse(code.as_ref()), Ok(code));
+    }
+}
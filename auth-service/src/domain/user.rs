@@ -4,15 +4,100 @@ use super::{email::Email, password::Password};
 pub struct User {
     pub email: Email,
     pub password: Password,
-    pub requires_2fa: bool
+    pub requires_2fa: bool,
+    // Bumped to invalidate every token issued to the user so far
+    pub token_version: i32,
+    // How the second factor is checked when `requires_2fa` is set
+    pub two_fa_method: TwoFAMethod,
+    // Base32 secret shared with the authenticator app. Only used once the enrollment is confirmed.
+    pub totp_secret: Option<String>,
+    // Roles granted to this user on top of the ones every user has
+    pub roles: Vec<String>,
+    // Only active accounts can log in or use their tokens. New users can't log in until they have
+    // confirmed they own the email address.
+    pub status: AccountStatus,
+    // Set by an admin, the user has to reset their password before they can log in again
+    pub password_reset_required: bool,
 }
 
+// Role required by the admin routes
+pub const ADMIN_ROLE: &str = "admin";
+
 impl User {
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> User {
         Self {
             email: email,
             password: password,
-            requires_2fa: requires_2fa
+            requires_2fa: requires_2fa,
+            token_version: 0,
+            two_fa_method: TwoFAMethod::default(),
+            totp_secret: None,
+            roles: Vec::new(),
+            status: AccountStatus::default(),
+            password_reset_required: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TwoFAMethod {
+    // 6-digit code sent by email on every login
+    #[default]
+    Email,
+    // RFC 6238 code generated by an authenticator app
+    Totp,
+}
+
+impl TwoFAMethod {
+    pub fn parse(method: &str) -> Result<Self, String> {
+        match method {
+            "email" => Ok(TwoFAMethod::Email),
+            "totp" => Ok(TwoFAMethod::Totp),
+            _ => Err("Invalid 2FA method".to_string()),
+        }
+    }
+}
+
+impl AsRef<str> for TwoFAMethod {
+    fn as_ref(&self) -> &str {
+        match self {
+            TwoFAMethod::Email => "email",
+            TwoFAMethod::Totp => "totp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AccountStatus {
+    Active,
+    // Suspended by an admin until they lift it
+    Suspended,
+    // Signed up but hasn't confirmed the email address yet
+    #[default]
+    PendingVerification,
+    // Closed, the account can't be used again
+    Deleted,
+}
+
+impl AccountStatus {
+    pub fn parse(status: &str) -> Result<Self, String> {
+        match status {
+            "active" => Ok(AccountStatus::Active),
+            "suspended" => Ok(AccountStatus::Suspended),
+            "pending-verification" => Ok(AccountStatus::PendingVerification),
+            "deleted" => Ok(AccountStatus::Deleted),
+            _ => Err("Invalid account status".to_string()),
+        }
+    }
+}
+
+impl AsRef<str> for AccountStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Suspended => "suspended",
+            AccountStatus::PendingVerification => "pending-verification",
+            AccountStatus::Deleted => "deleted",
         }
     }
 }
@@ -1,14 +1,23 @@
-use axum::{http::StatusCode, response::{IntoResponse, Response}, routing::post, serve::Serve, Json, Router};
+use axum::{http::StatusCode, response::{IntoResponse, Response}, routing::{delete, get, post}, serve::Serve, Json, Router};
 use domain::AuthAPIError;
 use tower_http::services::ServeDir;
 use std::error::Error;
-use axum::http::Method;
+use std::net::SocketAddr;
+use axum::extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo};
+use axum::middleware::{self, AddExtension};
+use axum::http::{header::RETRY_AFTER, HeaderValue, Method};
 use redis::{Client, RedisResult};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use tower_http::cors::CorsLayer;
-use routes::{login, logout, signup, verify_2fa, verify_token};
+use routes::{
+    admin_router, begin_webauthn_login, begin_webauthn_registration, change_password, confirm_password_reset, confirm_totp,
+    delete_account, enroll_totp, export_account_data, finish_webauthn_login, finish_webauthn_registration, get_sessions, introspect, jwks, login,
+    logout, logout_all, refresh, regenerate_recovery_codes, request_password_reset, resend_2fa,
+    resend_verification_email, revoke_session, signup, verify_2fa, verify_email, verify_token,
+};
+use utils::rate_limit::rate_limit;
 
 pub use app_state::AppState;
 pub use services::data_stores::HashmapUserStore;
@@ -16,8 +25,31 @@ pub use services::data_stores::PostgresUserStore;
 pub use services::data_stores::HashsetBannedTokenStore;
 pub use services::data_stores::HashmapTwoFACodeStore;
 pub use services::data_stores::MockEmailClient;
+pub use services::data_stores::{SmtpConfig, SmtpEmailClient, SmtpTls};
+pub use services::data_stores::{HttpEmailClient, HttpEmailConfig};
 pub use services::data_stores::RedisBannedTokenStore;
 pub use services::data_stores::RedisTwoFACodeStore;
+pub use services::data_stores::HashmapRefreshTokenStore;
+pub use services::data_stores::RedisRefreshTokenStore;
+pub use services::data_stores::HashmapSessionStore;
+pub use services::data_stores::RedisSessionStore;
+pub use services::data_stores::HashmapPasswordResetTokenStore;
+pub use services::data_stores::RedisPasswordResetTokenStore;
+pub use services::data_stores::HashmapEmailVerificationTokenStore;
+pub use services::data_stores::RedisEmailVerificationTokenStore;
+pub use services::data_stores::HashmapCredentialStore;
+pub use services::data_stores::PostgresCredentialStore;
+pub use services::data_stores::HashmapWebAuthnChallengeStore;
+pub use services::data_stores::RedisWebAuthnChallengeStore;
+pub use services::data_stores::HashmapLoginAttemptStore;
+pub use services::data_stores::RedisLoginAttemptStore;
+pub use services::data_stores::HashmapRateLimitStore;
+pub use services::data_stores::RedisRateLimitStore;
+pub use services::data_stores::HashmapEmailOutboxStore;
+pub use services::data_stores::PostgresEmailOutboxStore;
+pub use services::data_stores::HashmapAuditLogStore;
+pub use services::data_stores::PostgresAuditLogStore;
+pub use services::EmailOutboxWorker;
 
 pub mod app_state;
 pub mod domain;
@@ -27,7 +59,7 @@ pub mod utils;
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<IntoMakeServiceWithConnectInfo<Router, SocketAddr>, AddExtension<Router, ConnectInfo<SocketAddr>>>,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -44,8 +76,8 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
             // Allow cookies to be included in requests
             .allow_credentials(true)
             .allow_origin(allowed_origins);
@@ -55,14 +87,41 @@ impl Application {
         .route("/signup", post(signup))
         .route("/login", post(login))
         .route("/logout", post(logout))
+        .route("/logout-all", post(logout_all))
+        .route("/refresh", post(refresh))
+        .route("/sessions", get(get_sessions))
+        .route("/sessions/:id", delete(revoke_session))
+        .route("/me", delete(delete_account))
+        .route("/me/export", get(export_account_data))
+        .route("/change-password", post(change_password))
+        .route("/password-reset/request", post(request_password_reset))
+        .route("/password-reset/confirm", post(confirm_password_reset))
         .route("/verify-2fa", post(verify_2fa))
+        .route("/resend-2fa", post(resend_2fa))
+        .route("/2fa/totp/enroll", post(enroll_totp))
+        .route("/2fa/totp/confirm", post(confirm_totp))
+        .route("/2fa/recovery-codes", post(regenerate_recovery_codes))
+        .route("/webauthn/register/begin", post(begin_webauthn_registration))
+        .route("/webauthn/register/finish", post(finish_webauthn_registration))
+        .route("/webauthn/login/begin", post(begin_webauthn_login))
+        .route("/webauthn/login/finish", post(finish_webauthn_login))
+        .route("/verify-email", get(verify_email))
+        .route("/verify-email/resend", post(resend_verification_email))
         .route("/verify-token", post(verify_token))
+        .route("/introspect", post(introspect))
+        .route("/.well-known/jwks.json", get(jwks))
+        .nest("/admin", admin_router())
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), rate_limit))
         .with_state(app_state)
         .layer(cors);
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Expose the peer address to handlers, so sessions can record where they were created from
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application {address, server})
     }
@@ -80,12 +139,32 @@ pub struct ErrorResponse {
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
+        // Tells the client when a lockout ends
+        let retry_after = match self {
+            AuthAPIError::TooManyLoginAttempts(seconds) => Some(seconds),
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Incorrect credentials"),
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid credentials"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::InvalidClientCredentials => (StatusCode::UNAUTHORIZED, "Invalid client credentials"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::CredentialAlreadyExists => (StatusCode::CONFLICT, "Credential already registered"),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::BAD_REQUEST, "2FA not enabled"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Insufficient permissions"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::AccountSuspended => (StatusCode::FORBIDDEN, "Account suspended"),
+            AuthAPIError::AccountDeleted => (StatusCode::FORBIDDEN, "Account deleted"),
+            AuthAPIError::PasswordResetRequired => (StatusCode::FORBIDDEN, "Password reset required"),
+            AuthAPIError::TooManyTwoFAAttempts => (StatusCode::TOO_MANY_REQUESTS, "Too many failed 2FA attempts, log in again"),
+            AuthAPIError::TooManyLoginAttempts(_) => (StatusCode::TOO_MANY_REQUESTS, "Too many failed login attempts"),
             AuthAPIError::UnexpectedError => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
@@ -93,7 +172,11 @@ impl IntoResponse for AuthAPIError {
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
         });
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 }
 
@@ -1,10 +1,13 @@
 use std::sync::Arc;
+use std::time::Duration;
 use sqlx::{Executor, PgPool};
 use sqlx::postgres::PgPoolOptions;
-use auth_service::{Application, AppState, MockEmailClient, get_postgres_pool, PostgresUserStore, get_redis_client, RedisBannedTokenStore, RedisTwoFACodeStore};
+use auth_service::{Application, AppState, MockEmailClient, get_postgres_pool, PostgresUserStore, get_redis_client, RedisBannedTokenStore, RedisTwoFACodeStore, RedisRefreshTokenStore, RedisSessionStore, RedisPasswordResetTokenStore, RedisEmailVerificationTokenStore, PostgresCredentialStore, RedisWebAuthnChallengeStore, RedisLoginAttemptStore, RedisRateLimitStore, SmtpEmailClient, HttpEmailClient, PostgresEmailOutboxStore, EmailOutboxWorker, PostgresAuditLogStore};
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use auth_service::utils::constants::{prod, DATABASE_URL, REDIS_HOST_NAME};
+use auth_service::app_state::{EmailClientType, UserStoreType};
+use auth_service::utils::constants::{prod, ADMIN_EMAILS, DATABASE_URL, REDIS_HOST_NAME, SMTP_CONFIG, EMAIL_API_CONFIG};
+use auth_service::utils::roles::seed_admins;
 
 #[tokio::main]
 async fn main() {
@@ -12,8 +15,10 @@ async fn main() {
     let pg_pool = configure_postgresql().await;
 
     // let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
-    let user_store =
-        Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+    let user_store: UserStoreType =
+        Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+    seed_admins(&user_store, &ADMIN_EMAILS).await.expect("Failed to grant the admin role to ADMIN_EMAILS");
+
     let redis_conn = Arc::new(RwLock::new(configure_redis()));
     // let banned_token_store =
     //     Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
@@ -23,13 +28,84 @@ async fn main() {
     //     Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
 
     let two_fa_code_store =
-        Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+        Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
+
+    // let refresh_token_store =
+    //     Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+
+    let refresh_token_store =
+        Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+
+    // let session_store =
+    //     Arc::new(RwLock::new(HashmapSessionStore::default()));
+
+    let session_store =
+        Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
+
+    // let password_reset_token_store =
+    //     Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+
+    let password_reset_token_store =
+        Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn.clone())));
+
+    // let email_verification_token_store =
+    //     Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default()));
+
+    let email_verification_token_store =
+        Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(redis_conn.clone())));
+
+    // let credential_store =
+    //     Arc::new(RwLock::new(HashmapCredentialStore::default()));
+
+    let credential_store =
+        Arc::new(RwLock::new(PostgresCredentialStore::new(pg_pool.clone())));
+
+    // let webauthn_challenge_store =
+    //     Arc::new(RwLock::new(HashmapWebAuthnChallengeStore::default()));
+
+    let webauthn_challenge_store =
+        Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(redis_conn.clone())));
+
+    // let login_attempt_store =
+    //     Arc::new(RwLock::new(HashmapLoginAttemptStore::default()));
+
+    let login_attempt_store =
+        Arc::new(RwLock::new(RedisLoginAttemptStore::new(redis_conn.clone())));
+
+    // let rate_limit_store =
+    //     Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+
+    let rate_limit_store =
+        Arc::new(RwLock::new(RedisRateLimitStore::new(redis_conn)));
+
+    let email_client: EmailClientType = match (EMAIL_API_CONFIG.as_ref(), SMTP_CONFIG.as_ref()) {
+        (Some(config), _) =>
+            Arc::new(HttpEmailClient::new(config).expect("Failed to configure HTTP email client")),
+        (None, Some(config)) =>
+            Arc::new(SmtpEmailClient::new(config).expect("Failed to configure SMTP email client")),
+        (None, None) => Arc::new(MockEmailClient),
+    };
+
+    // let email_outbox =
+    //     Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+
+    let email_outbox =
+        Arc::new(RwLock::new(PostgresEmailOutboxStore::new(pg_pool.clone())));
+
+    // Requests only queue emails, the worker delivers them in the background
+    let email_outbox_worker = EmailOutboxWorker::new(email_outbox.clone(), email_client, Duration::from_secs(1));
+    tokio::spawn(email_outbox_worker.run());
+
+    // let audit_log_store =
+    //     Arc::new(RwLock::new(HashmapAuditLogStore::default()));
 
-    let email_client =
-        Arc::new(MockEmailClient);
+    let audit_log_store =
+        Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool)));
 
     let app_state = AppState::new(user_store, banned_token_store, two_fa_code_store,
-                                  email_client);
+                                  refresh_token_store, session_store, password_reset_token_store,
+                                  email_verification_token_store, credential_store, webauthn_challenge_store,
+                                  login_attempt_store, rate_limit_store, email_outbox, audit_log_store);
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
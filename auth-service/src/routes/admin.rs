@@ -0,0 +1,259 @@
+use axum::{http::StatusCode, response::IntoResponse, routing::{get, post}, Json, Router};
+use axum::extract::{Path, Query, State};
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::{AccountStatus, AuthAPIError, Email, LoginAttemptKey, User, UserStoreError};
+use crate::utils::auth;
+use crate::utils::roles::{Admin, RequireRole};
+use super::send_password_reset_email;
+
+// This value determines how many users a page lists when the request doesn't say
+pub const DEFAULT_USERS_PER_PAGE: usize = 20;
+
+// This value determines the most users a single page can list
+pub const MAX_USERS_PER_PAGE: usize = 100;
+
+// Routes nested under `/admin`, every one of them requires the admin role
+pub fn admin_router() -> Router<AppState> {
+    Router::new()
+        .route("/users", get(list_users))
+        .route("/users/:email", get(get_user).delete(delete_user))
+        .route("/users/:email/disable", post(disable_user))
+        .route("/users/:email/enable", post(enable_user))
+        .route("/users/:email/force-password-reset", post(force_password_reset))
+        .route("/users/:email/requires-2fa", post(set_requires_2fa))
+        .route("/users/:email/unlock", post(unlock_user))
+}
+
+pub async fn list_users(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_USERS_PER_PAGE).clamp(1, MAX_USERS_PER_PAGE);
+
+    let email_search = query.email.as_deref().map(str::trim).filter(|search| !search.is_empty());
+
+    let user_page = state.user_store
+        .read()
+        .await
+        .list_users(email_search, (page - 1).saturating_mul(per_page), per_page)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let response = Json(AdminUserListResponse {
+        users: user_page.users.iter().map(|user| AdminUserResponse::new(user, None)).collect(),
+        page,
+        per_page,
+        total: user_page.total,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+pub async fn get_user(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(&email)?;
+
+    Ok((StatusCode::OK, Json(user_response(&state, &email).await?)))
+}
+
+pub async fn disable_user(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(&email)?;
+
+    map_user_store_error(state.user_store.write().await.set_status(&email, AccountStatus::Suspended).await)?;
+
+    // Logging the user out everywhere makes the change apply at once, not only on their next login
+    revoke_tokens(&state, &email).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&state, &email).await?)))
+}
+
+pub async fn enable_user(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(&email)?;
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::UserNotFound),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    // Only a suspension is lifted, pending verification stays up to the user.
+    // A suspended account is activated even if its email was never verified.
+    if user.status == AccountStatus::Suspended {
+        map_user_store_error(state.user_store.write().await.set_status(&email, AccountStatus::Active).await)?;
+    }
+
+    Ok((StatusCode::OK, Json(user_response(&state, &email).await?)))
+}
+
+// Logs the user out and keeps them from logging in until they've set a new password through the emailed link
+pub async fn force_password_reset(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(&email)?;
+
+    map_user_store_error(state.user_store.write().await.set_password_reset_required(&email, true).await)?;
+
+    revoke_tokens(&state, &email).await?;
+
+    send_password_reset_email(&state, &email).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&state, &email).await?)))
+}
+
+pub async fn set_requires_2fa(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+    Json(request): Json<SetRequires2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(&email)?;
+
+    map_user_store_error(state.user_store.write().await.set_requires_2fa(&email, request.requires_2fa).await)?;
+
+    Ok((StatusCode::OK, Json(user_response(&state, &email).await?)))
+}
+
+// Lifts a lockout caused by failed logins against the account
+pub async fn unlock_user(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(&email)?;
+
+    // Fails for unknown users, so a typo doesn't look like a successful unlock
+    let response = user_response(&state, &email).await?;
+
+    state.login_attempt_store
+        .write()
+        .await
+        .reset(&LoginAttemptKey::Email(email.clone()))
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse { locked_out_seconds: None, ..response })))
+}
+
+pub async fn delete_user(
+    _: RequireRole<Admin>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(&email)?;
+
+    map_user_store_error(state.user_store.write().await.delete_user(&email).await)?;
+
+    auth::remove_all_sessions(&email, state.session_store.clone(), state.refresh_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(StatusCode::OK)
+}
+
+fn parse_email(email: &str) -> Result<Email, AuthAPIError> {
+    Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+fn map_user_store_error(result: Result<(), UserStoreError>) -> Result<(), AuthAPIError> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::UserNotFound),
+        Err(_) => Err(AuthAPIError::UnexpectedError),
+    }
+}
+
+// Rejects every JWT and refresh token issued to the user so far
+async fn revoke_tokens(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    map_user_store_error(state.user_store.write().await.increment_token_version(email).await)?;
+
+    auth::remove_all_sessions(email, state.session_store.clone(), state.refresh_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+async fn user_response(state: &AppState, email: &Email) -> Result<AdminUserResponse, AuthAPIError> {
+    let user = match state.user_store.read().await.get_user(email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::UserNotFound),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let locked_out_seconds = state.login_attempt_store
+        .read()
+        .await
+        .get_lockout(&LoginAttemptKey::Email(email.clone()))
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(AdminUserResponse::new(&user, locked_out_seconds))
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    pub page: Option<usize>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<usize>,
+    // Part of the email to search for
+    pub email: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SetRequires2FARequest {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AdminUserResponse {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: String,
+    pub roles: Vec<String>,
+    pub status: String,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+    // Only looked up for single users, listing leaves it out
+    #[serde(rename = "lockedOutSeconds", skip_serializing_if = "Option::is_none")]
+    pub locked_out_seconds: Option<i64>,
+}
+
+impl AdminUserResponse {
+    fn new(user: &User, locked_out_seconds: Option<i64>) -> Self {
+        AdminUserResponse {
+            email: user.email.as_ref().to_owned(),
+            requires_2fa: user.requires_2fa,
+            two_fa_method: user.two_fa_method.as_ref().to_owned(),
+            roles: auth::user_roles(user),
+            status: user.status.as_ref().to_owned(),
+            password_reset_required: user.password_reset_required,
+            locked_out_seconds,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUserListResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub page: usize,
+    #[serde(rename = "perPage")]
+    pub per_page: usize,
+    pub total: usize,
+}
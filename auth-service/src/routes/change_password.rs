@@ -0,0 +1,125 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::AppState;
+use crate::domain::{AuditEventType, AuthAPIError, Email, Password, UserStoreError};
+use crate::utils::audit::record_audit_event;
+use crate::utils::auth;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::JWT_COOKIE_NAME;
+
+pub async fn change_password(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+
+    match try_change_password(&state, &client, &jar, request).await {
+        Ok(Some((auth_cookie, refresh_cookie))) => (jar.add(auth_cookie).add(refresh_cookie), Ok(StatusCode::OK)),
+        Ok(None) => (jar, Ok(StatusCode::OK)),
+        Err(e) => (jar, Err(e)),
+    }
+}
+
+// Returns the new cookies of the caller when the other sessions were revoked
+async fn try_change_password(
+    state: &AppState,
+    client: &ClientInfo,
+    jar: &CookieJar,
+    request: ChangePasswordRequest,
+) -> Result<Option<(Cookie<'static>, Cookie<'static>)>, AuthAPIError> {
+
+    let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
+
+    let claims = match auth::validate_token(
+        cookie.value(),
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    ).await {
+        Ok(claims) => claims,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    let email = Email::parse(&claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let current_password = match Password::parse(&request.current_password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    let new_password = match Password::parse(&request.new_password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    {
+        let mut user_store = state.user_store.write().await;
+
+        // A stolen session alone isn't enough to take over the account
+        match user_store.validate_user(&email, &current_password).await {
+            Ok(_) => (),
+            Err(UserStoreError::InvalidCredentials) => return Err(AuthAPIError::IncorrectCredentials),
+            Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+            Err(_) => return Err(AuthAPIError::UnexpectedError),
+        }
+
+        if user_store.update_password(&email, new_password).await.is_err() {
+            return Err(AuthAPIError::UnexpectedError);
+        }
+
+        record_audit_event(state, &email, AuditEventType::PasswordChanged, client).await;
+
+        if !request.revoke_other_sessions {
+            return Ok(None);
+        }
+
+        // Bumping the version also rejects refresh tokens whose sessions aren't listed anymore
+        if user_store.increment_token_version(&email).await.is_err() {
+            return Err(AuthAPIError::UnexpectedError);
+        }
+    }
+
+    if auth::remove_all_sessions(&email, state.session_store.clone(), state.refresh_token_store.clone())
+        .await
+        .is_err() {
+        return Err(AuthAPIError::UnexpectedError);
+    }
+
+    // The caller's own tokens were revoked with the rest, so they get a fresh session
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let refresh_family_id = Uuid::new_v4().to_string();
+
+    let auth_cookie = auth::generate_auth_cookie(
+        &user,
+        client,
+        Some(refresh_family_id.clone()),
+        state.session_store.clone(),
+    )
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let refresh_cookie = auth::generate_refresh_cookie(&user, refresh_family_id, state.refresh_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(Some((auth_cookie, refresh_cookie)))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+    #[serde(rename = "revokeOtherSessions", default)]
+    pub revoke_other_sessions: bool,
+}
@@ -0,0 +1,93 @@
+use axum::{http::StatusCode, response::IntoResponse, Form, Json};
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::AuthAPIError;
+use crate::utils::auth::{self, Claims};
+use crate::utils::client_credentials::IntrospectionClient;
+
+// Token introspection as described in RFC 7662, for services which need to know who the user is
+pub async fn introspect(
+    State(state): State<AppState>,
+    _client: IntrospectionClient,
+    Form(request): Form<IntrospectRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    // Expired, banned, revoked and malformed tokens are all reported the same way
+    let response = match auth::validate_token(
+        &request.token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    ).await {
+        Ok(claims) => IntrospectResponse::from(claims),
+        Err(_) => IntrospectResponse::inactive(),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectRequest {
+    pub token: String,
+    // Only JWT auth tokens can be introspected, so the hint is accepted but not needed
+    pub token_type_hint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    // Space separated roles, following the format of the OAuth `scope` parameter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+}
+
+impl IntrospectResponse {
+    fn inactive() -> Self {
+        IntrospectResponse {
+            active: false,
+            iss: None,
+            sub: None,
+            aud: None,
+            exp: None,
+            iat: None,
+            nbf: None,
+            jti: None,
+            scope: None,
+            token_type: None,
+        }
+    }
+}
+
+impl From<Claims> for IntrospectResponse {
+    fn from(claims: Claims) -> Self {
+        IntrospectResponse {
+            active: true,
+            iss: Some(claims.iss),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            nbf: Some(claims.nbf),
+            jti: Some(claims.jti),
+            scope: Some(claims.roles.join(" ")),
+            token_type: Some("Bearer".to_owned()),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use crate::utils::constants::JWT_KEYRING;
+
+// Public keys downstream services use to verify JWT auth tokens without calling /verify-token
+pub async fn jwks() -> impl IntoResponse {
+    (StatusCode::OK, Json(JWT_KEYRING.jwk_set()))
+}
@@ -2,11 +2,19 @@ use axum::{http::StatusCode, response::IntoResponse, Json};
 use axum::extract::State;
 use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 use crate::AppState;
-use crate::domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode, UserStoreError};
+use crate::domain::{
+    AccountStatus, AuditEventType, AuthAPIError, Email, LoginAttemptId, LoginAttemptKey, Password, TwoFACode,
+    TwoFAMethod, User, UserStoreError,
+};
+use crate::utils::audit::record_audit_event;
 use crate::utils::auth;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::email_templates::{queue_email, NewLoginEmail, TwoFACodeEmail};
 
-pub async fn login(State(state): State<AppState>, jar: CookieJar, Json(request): Json<LoginRequest>)
+pub async fn login(State(state): State<AppState>, client: ClientInfo, jar: CookieJar,
+                   Json(request): Json<LoginRequest>)
                    -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
 
     let email = match Email::parse(&request.email) {
@@ -19,39 +27,110 @@ pub async fn login(State(state): State<AppState>, jar: CookieJar, Json(request):
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let user_store = state.user_store.read().await;
+    let attempt_keys = login_attempt_keys(&email, &client);
 
-    match user_store.validate_user(&email, &password).await {
+    // Locked out clients are turned away before their password gets hashed
+    match get_lockout(&state, &attempt_keys).await {
+        Ok(None) => (),
+        Ok(Some(retry_after)) => return (jar, Err(AuthAPIError::TooManyLoginAttempts(retry_after))),
+        Err(e) => return (jar, Err(e)),
+    }
+
+    // The user store isn't kept locked, `handle_2fa` needs the 2FA code store
+    let validation = state.user_store.read().await.validate_user(&email, &password).await;
+
+    match validation {
         Ok(_) => (),
-        Err(UserStoreError::InvalidCredentials) =>
-            return (jar, Err(AuthAPIError::IncorrectCredentials)),
-        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(UserStoreError::InvalidCredentials) | Err(UserStoreError::UserNotFound) => {
+            if let Err(e) = record_login_failure(&state, &attempt_keys).await {
+                return (jar, Err(e));
+            }
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        },
         Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
     }
 
-    let user = match user_store.get_user(&email).await {
+    // Only the account starts over, otherwise a single valid account would let its owner
+    // keep guessing other accounts' passwords from the same address
+    if state.login_attempt_store.write().await.reset(&LoginAttemptKey::Email(email.clone())).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let user = match state.user_store.read().await.get_user(&email).await {
         Ok(user) => user,
         Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
         Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
     };
 
-    let auth_cookie = match auth::generate_auth_cookie(&user.email) {
-        Ok(auth_cookie) => auth_cookie,
-        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
-    };
-
-    let updated_jar = jar.add(auth_cookie);
+    if let Err(e) = check_account_usable(&user) {
+        return (jar, Err(e));
+    }
 
     match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, updated_jar).await,
-        false => handle_no_2fa(updated_jar).await,
+        true => handle_2fa(&user, &state, jar).await,
+        false => handle_no_2fa(&user, &client, &state, jar).await,
     }
 
     /*(updated_jar, Ok(StatusCode::OK.into_response()))*/
 }
 
+// Only active accounts can log in, no matter which factors are presented
+pub(crate) fn check_account_usable(user: &User) -> Result<(), AuthAPIError> {
+    match user.status {
+        AccountStatus::Active => {},
+        AccountStatus::Suspended => return Err(AuthAPIError::AccountSuspended),
+        AccountStatus::PendingVerification => return Err(AuthAPIError::EmailNotVerified),
+        AccountStatus::Deleted => return Err(AuthAPIError::AccountDeleted),
+    }
+
+    if user.password_reset_required {
+        return Err(AuthAPIError::PasswordResetRequired);
+    }
+
+    Ok(())
+}
+
+// Failed logins count against the account and against the address they came from
+fn login_attempt_keys(email: &Email, client: &ClientInfo) -> Vec<LoginAttemptKey> {
+    let mut keys = vec![LoginAttemptKey::Email(email.clone())];
+
+    if let Some(ip) = &client.ip {
+        keys.push(LoginAttemptKey::Ip(ip.clone()));
+    }
+
+    keys
+}
+
+// Seconds until the longest of the lockouts ends
+async fn get_lockout(state: &AppState, keys: &[LoginAttemptKey]) -> Result<Option<i64>, AuthAPIError> {
+    let login_attempt_store = state.login_attempt_store.read().await;
+
+    let mut lockout = None;
+
+    for key in keys {
+        match login_attempt_store.get_lockout(key).await {
+            Ok(seconds) => lockout = lockout.max(seconds),
+            Err(_) => return Err(AuthAPIError::UnexpectedError),
+        }
+    }
+
+    Ok(lockout)
+}
+
+async fn record_login_failure(state: &AppState, keys: &[LoginAttemptKey]) -> Result<(), AuthAPIError> {
+    let mut login_attempt_store = state.login_attempt_store.write().await;
+
+    for key in keys {
+        if login_attempt_store.record_failure(key).await.is_err() {
+            return Err(AuthAPIError::UnexpectedError);
+        }
+    }
+
+    Ok(())
+}
+
 async fn handle_2fa(
-    email: &Email,
+    user: &User,
     state: &AppState,
     jar: CookieJar,
 ) -> (
@@ -65,7 +144,7 @@ async fn handle_2fa(
     let two_fa_code = TwoFACode::default();
 
     match state.two_fa_code_store.write().await.add_code(
-        email.clone(),
+        user.email.clone(),
         login_attempt_id.clone(),
         two_fa_code.clone(),
     ).await {
@@ -73,13 +152,11 @@ async fn handle_2fa(
         _ => return (jar, Err(AuthAPIError::UnexpectedError)),
     }
 
-    match state.email_client.send_email(
-        &email,
-        "Your 2FA Code",
-        two_fa_code.as_ref(),
-    ).await {
-        Ok(_) => (),
-        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    // Authenticator app users generate the code themselves, the stored one only ties the attempt to the login
+    if user.two_fa_method == TwoFAMethod::Email {
+        if let Err(e) = send_2fa_code(state, &user.email, &two_fa_code).await {
+            return (jar, Err(e));
+        }
     }
 
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
@@ -90,13 +167,76 @@ async fn handle_2fa(
     (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
 }
 
+pub(crate) async fn send_2fa_code(state: &AppState, email: &Email, code: &TwoFACode) -> Result<(), AuthAPIError> {
+    queue_email(state, email, &TwoFACodeEmail { code: code.as_ref() }).await
+}
+
+// Warn the user about a login from a client none of their active sessions came from.
+// Must be called before the session of the new login is created. The login goes ahead even if
+// the alert can't be sent, as it only informs the user.
+pub(crate) async fn send_new_login_alert(state: &AppState, email: &Email, client: &ClientInfo) {
+    let _ = try_send_new_login_alert(state, email, client).await;
+}
+
+async fn try_send_new_login_alert(state: &AppState, email: &Email, client: &ClientInfo) -> Result<(), AuthAPIError> {
+    let sessions = state.session_store.read().await
+        .get_sessions(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let known_client = sessions
+        .iter()
+        .any(|session| session.ip == client.ip && session.user_agent == client.user_agent);
+
+    if known_client {
+        return Ok(());
+    }
+
+    queue_email(state, email, &NewLoginEmail {
+        time: &chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        ip: client.ip.as_deref().unwrap_or("unknown"),
+        user_agent: client.user_agent.as_deref().unwrap_or("unknown"),
+    }).await
+}
+
 async fn handle_no_2fa(
+    user: &User,
+    client: &ClientInfo,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
 
+    send_new_login_alert(state, &user.email, client).await;
+
+    // A fresh login starts a new refresh token family
+    let refresh_family_id = Uuid::new_v4().to_string();
+
+    let auth_cookie = match auth::generate_auth_cookie(
+        user,
+        client,
+        Some(refresh_family_id.clone()),
+        state.session_store.clone(),
+    ).await {
+        Ok(auth_cookie) => auth_cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    let refresh_cookie = match auth::generate_refresh_cookie(
+        user,
+        refresh_family_id,
+        state.refresh_token_store.clone(),
+    ).await {
+        Ok(refresh_cookie) => refresh_cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    record_audit_event(state, &user.email, AuditEventType::Login, client).await;
+
+    let jar = jar.add(auth_cookie).add(refresh_cookie);
+
     let response = (StatusCode::OK, Json(LoginResponse::RegularAuth));
 
     (jar, Ok(response))
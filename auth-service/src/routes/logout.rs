@@ -2,9 +2,9 @@ use axum::{http::StatusCode, response::IntoResponse};
 use axum::extract::State;
 use axum_extra::extract::CookieJar;
 use crate::AppState;
-use crate::domain::AuthAPIError;
+use crate::domain::{AuthAPIError, RefreshToken};
 use crate::utils::auth;
-use crate::utils::constants::JWT_COOKIE_NAME;
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
 
 pub async fn logout(State(state): State<AppState>, jar: CookieJar)
     -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -23,10 +23,15 @@ pub async fn logout(State(state): State<AppState>, jar: CookieJar)
     // If the token is valid you can ignore the returned claims for now.
     // Return AuthAPIError::InvalidToken is validation fails.
 
-    match auth::validate_token(&token, state.banned_token_store.clone()).await {
-        Ok(_) => (),
+    let claims = match auth::validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    ).await {
+        Ok(claims) => claims,
         Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
-    }
+    };
 
     let jar = jar.remove(JWT_COOKIE_NAME);
 
@@ -38,5 +43,24 @@ pub async fn logout(State(state): State<AppState>, jar: CookieJar)
         .await
         .unwrap();
 
+    if state.session_store.write().await.remove_session(&claims.jti).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    // Revoke the refresh token family as well, so the session can't be renewed after logout
+    if let Some(refresh_cookie) = jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        if let Ok(refresh_token) = RefreshToken::parse(refresh_cookie.value().to_owned()) {
+            let mut refresh_token_store = state.refresh_token_store.write().await;
+
+            if let Ok(record) = refresh_token_store.consume_token(&refresh_token).await {
+                if refresh_token_store.revoke_family(&record.family_id).await.is_err() {
+                    return (jar, Err(AuthAPIError::UnexpectedError));
+                }
+            }
+        }
+    }
+
+    let jar = jar.remove(REFRESH_TOKEN_COOKIE_NAME);
+
     (jar, Ok(StatusCode::OK))
 }
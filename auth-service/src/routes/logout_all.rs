@@ -0,0 +1,53 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use crate::AppState;
+use crate::domain::{AuditEventType, AuthAPIError, Email};
+use crate::utils::audit::record_audit_event;
+use crate::utils::auth;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+
+pub async fn logout_all(State(state): State<AppState>, client: ClientInfo, jar: CookieJar)
+    -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+
+    let cookie = match jar.get(JWT_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = cookie.value().to_owned();
+
+    let claims = match auth::validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    ).await {
+        Ok(claims) => claims,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let email = match Email::parse(&claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Bumping the version rejects every JWT and refresh token issued so far,
+    // whether or not it is still listed in the session registry
+    if state.user_store.write().await.increment_token_version(&email).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    if auth::remove_all_sessions(&email, state.session_store.clone(), state.refresh_token_store.clone())
+        .await
+        .is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    record_audit_event(&state, &email, AuditEventType::LoggedOutEverywhere, &client).await;
+
+    let jar = jar.remove(JWT_COOKIE_NAME).remove(REFRESH_TOKEN_COOKIE_NAME);
+
+    (jar, Ok(StatusCode::OK))
+}
@@ -0,0 +1,259 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::{AuthAPIError, Email, LoginAttemptKey, Password, TwoFACodeStoreError, UserStoreError};
+use crate::utils::auth::{self, AuthenticatedUser};
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use crate::utils::email_templates::{queue_email, AccountDeletedEmail};
+
+use super::SessionResponse;
+
+// Everything stored about the user making the request
+pub async fn export_account_data(
+    State(state): State<AppState>,
+    AuthenticatedUser { user, claims }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let email = user.email.clone();
+
+    let sessions = state.session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let passkeys = state.credential_store
+        .read()
+        .await
+        .get_credentials(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let emails = state.email_outbox
+        .read()
+        .await
+        .get_emails(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let audit_events = state.audit_log_store
+        .read()
+        .await
+        .get_events(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let response = Json(AccountExportResponse {
+        exported_at: Utc::now().timestamp(),
+        profile: ProfileExport {
+            email: user.email.as_ref().to_owned(),
+            status: user.status.as_ref().to_owned(),
+            roles: auth::user_roles(&user),
+            password_reset_required: user.password_reset_required,
+        },
+        // Secrets, hashes and keys are left out, they are of no use to the user and would only leak
+        two_factor: TwoFactorExport {
+            enabled: user.requires_2fa,
+            method: user.two_fa_method.as_ref().to_owned(),
+            totp_enrolled: user.totp_secret.is_some(),
+            passkeys: passkeys
+                .into_iter()
+                .map(|credential| PasskeyExport {
+                    id: credential.id,
+                    created_at: credential.created_at,
+                    sign_count: credential.sign_count,
+                })
+                .collect(),
+        },
+        sessions: sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                current: session.id == claims.jti,
+                id: session.id,
+                created_at: session.created_at,
+                expires_at: session.expires_at,
+                user_agent: session.user_agent,
+                ip: session.ip,
+            })
+            .collect(),
+        audit_events: audit_events
+            .into_iter()
+            .map(|event| AuditEventExport {
+                event_type: event.event_type.as_str().to_owned(),
+                ip: event.ip,
+                user_agent: event.user_agent,
+                created_at: event.created_at,
+            })
+            .collect(),
+        // Their bodies may hold links and codes that still work, so only what was sent and when is included
+        emails: emails
+            .into_iter()
+            .map(|email| EmailExport {
+                subject: email.message.subject,
+                status: email.status.as_str().to_owned(),
+                created_at: email.created_at,
+            })
+            .collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+pub async fn delete_account(
+    State(state): State<AppState>,
+    AuthenticatedUser { user, .. }: AuthenticatedUser,
+    jar: CookieJar,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+
+    let result = try_delete_account(&state, &jar, user.email, request).await;
+
+    // The cookies are useless once the account is gone
+    let jar = match result {
+        Ok(_) => jar.remove(JWT_COOKIE_NAME).remove(REFRESH_TOKEN_COOKIE_NAME),
+        Err(_) => jar,
+    };
+
+    (jar, result.map(|_| StatusCode::OK))
+}
+
+async fn try_delete_account(
+    state: &AppState,
+    jar: &CookieJar,
+    email: Email,
+    request: DeleteAccountRequest,
+) -> Result<(), AuthAPIError> {
+
+    let password = match Password::parse(&request.password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    // Deleting can't be undone, so a stolen session alone isn't enough
+    match state.user_store.read().await.validate_user(&email, &password).await {
+        Ok(_) => (),
+        Err(UserStoreError::InvalidCredentials) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    // Tokens are revoked first, so nothing issued to the user outlives the account
+    if let Some(cookie) = jar.get(JWT_COOKIE_NAME) {
+        state.banned_token_store
+            .write()
+            .await
+            .add_token(cookie.value().to_owned())
+            .await
+            .map_err(|_| AuthAPIError::UnexpectedError)?;
+    }
+
+    auth::remove_all_sessions(&email, state.session_store.clone(), state.refresh_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    match state.two_fa_code_store.write().await.remove_code(&email).await {
+        Ok(_) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => (),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    state.login_attempt_store
+        .write()
+        .await
+        .reset(&LoginAttemptKey::Email(email.clone()))
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state.email_outbox
+        .write()
+        .await
+        .delete_emails(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state.audit_log_store
+        .write()
+        .await
+        .delete_events(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    // Passkeys and recovery codes go with the user. Pending password reset and email verification
+    // tokens expire on their own and can't be used without the user.
+    match state.user_store.write().await.delete_user(&email).await {
+        Ok(_) => (),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    queue_email(state, &email, &AccountDeletedEmail {
+        time: &Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+    }).await
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountExportResponse {
+    #[serde(rename = "exportedAt")]
+    pub exported_at: i64,
+    pub profile: ProfileExport,
+    #[serde(rename = "twoFactor")]
+    pub two_factor: TwoFactorExport,
+    pub sessions: Vec<SessionResponse>,
+    #[serde(rename = "auditEvents")]
+    pub audit_events: Vec<AuditEventExport>,
+    pub emails: Vec<EmailExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileExport {
+    pub email: String,
+    pub status: String,
+    pub roles: Vec<String>,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorExport {
+    pub enabled: bool,
+    pub method: String,
+    #[serde(rename = "totpEnrolled")]
+    pub totp_enrolled: bool,
+    pub passkeys: Vec<PasskeyExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyExport {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "signCount")]
+    pub sign_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventExport {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub ip: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailExport {
+    pub subject: String,
+    pub status: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
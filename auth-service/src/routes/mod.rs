@@ -1,12 +1,40 @@
+mod admin;
+mod change_password;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod me;
+mod password_reset;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webauthn;
 
 // re-export items from sub-modules
+pub use admin::*;
+pub use change_password::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use logout_all::*;
+pub use me::*;
+pub use password_reset::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use resend_2fa::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
+pub use webauthn::*;
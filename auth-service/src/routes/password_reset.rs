@@ -0,0 +1,121 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::{
+    AuditEventType, AuthAPIError, Email, Password, PasswordResetToken, PasswordResetTokenStoreError, UserStoreError,
+};
+use crate::utils::audit::record_audit_event;
+use crate::utils::auth::{self, PASSWORD_RESET_TOKEN_TTL_SECONDS};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::PASSWORD_RESET_URL;
+use crate::utils::email_templates::{queue_email, PasswordResetEmail};
+
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let email = match Email::parse(&request.email) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let response = Json(PasswordResetResponse {
+        message: "If the account exists, a password reset link has been sent".to_owned(),
+    });
+
+    // Unknown emails get the same response, so the endpoint can't be used to find out who has an account
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(_) => (),
+        Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    send_password_reset_email(&state, &email).await?;
+
+    Ok((StatusCode::OK, response))
+}
+
+// Issue a new password reset token and email the link to the user
+pub(crate) async fn send_password_reset_email(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    let token = PasswordResetToken::default();
+
+    match state.password_reset_token_store.write().await.add_token(token.clone(), email.clone()).await {
+        Ok(_) => (),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let url = format!("{}?token={}", PASSWORD_RESET_URL.as_str(), token.as_ref());
+
+    queue_email(state, email, &PasswordResetEmail {
+        url: &url,
+        expires_in_minutes: PASSWORD_RESET_TOKEN_TTL_SECONDS / 60,
+    }).await
+}
+
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(request): Json<PasswordResetConfirmRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let token = match PasswordResetToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    // The password is checked first, so a rejected password doesn't use up the token
+    let password = match Password::parse(&request.new_password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let email = match state.password_reset_token_store.write().await.consume_token(&token).await {
+        Ok(email) => email,
+        Err(PasswordResetTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    {
+        let mut user_store = state.user_store.write().await;
+
+        match user_store.update_password(&email, password).await {
+            Ok(_) => (),
+            Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+            Err(_) => return Err(AuthAPIError::UnexpectedError),
+        }
+
+        record_audit_event(&state, &email, AuditEventType::PasswordReset, &client).await;
+
+        // Whoever knew the old password may still be logged in, so every issued token is rejected
+        if user_store.increment_token_version(&email).await.is_err() {
+            return Err(AuthAPIError::UnexpectedError);
+        }
+    }
+
+    if auth::remove_all_sessions(&email, state.session_store.clone(), state.refresh_token_store.clone())
+        .await
+        .is_err() {
+        return Err(AuthAPIError::UnexpectedError);
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirmRequest {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
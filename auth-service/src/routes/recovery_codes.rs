@@ -0,0 +1,57 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::{AuthAPIError, Email, Password, RecoveryCode, UserStoreError, RECOVERY_CODE_COUNT};
+use crate::utils::auth::AuthenticatedUser;
+
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    AuthenticatedUser { user, .. }: AuthenticatedUser,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let password = match Password::parse(&request.password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    // Fresh codes bypass the second factor, so a stolen session alone isn't enough to get them
+    match state.user_store.read().await.validate_user(&user.email, &password).await {
+        Ok(_) => (),
+        Err(UserStoreError::InvalidCredentials) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    if !user.requires_2fa {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    let response = Json(RecoveryCodesResponse {
+        recovery_codes: issue_recovery_codes(&state, &user.email).await?,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Replaces the user's recovery codes, returning the only plaintext copy of the new ones
+pub(crate) async fn issue_recovery_codes(state: &AppState, email: &Email) -> Result<Vec<String>, AuthAPIError> {
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT).map(|_| RecoveryCode::default()).collect();
+
+    match state.user_store.write().await.set_recovery_codes(email, codes.clone()).await {
+        Ok(_) => Ok(codes.iter().map(|code| code.as_ref().to_owned()).collect()),
+        Err(_) => Err(AuthAPIError::UnexpectedError),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
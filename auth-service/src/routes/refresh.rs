@@ -0,0 +1,95 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use crate::AppState;
+use crate::domain::{AuthAPIError, Email, RefreshToken, RefreshTokenStoreError};
+use crate::utils::auth;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use super::check_account_usable;
+
+pub async fn refresh(State(state): State<AppState>, client: ClientInfo, jar: CookieJar)
+    -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+
+    let cookie = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match RefreshToken::parse(cookie.value().to_owned()) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let consumed = state.refresh_token_store.write().await.consume_token(&token).await;
+
+    let record = match consumed {
+        Ok(record) => record,
+        Err(RefreshTokenStoreError::TokenReused(record)) => {
+            // A refresh token can only be exchanged once. Seeing it again means it has leaked,
+            // so every token issued from the same login is revoked.
+            if state.refresh_token_store.write().await.revoke_family(&record.family_id).await.is_err() {
+                return (jar, Err(AuthAPIError::UnexpectedError));
+            }
+
+            if let Ok(email) = Email::parse(&record.email) {
+                if auth::remove_family_sessions(&email, &record.family_id, state.session_store.clone())
+                    .await
+                    .is_err() {
+                    return (jar, Err(AuthAPIError::UnexpectedError));
+                }
+            }
+
+            let jar = jar.remove(JWT_COOKIE_NAME).remove(REFRESH_TOKEN_COOKIE_NAME);
+
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(RefreshTokenStoreError::UnexpectedError) =>
+            return (jar, Err(AuthAPIError::UnexpectedError)),
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let email = match Email::parse(&record.email) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    // Refresh tokens issued before the user logged out everywhere are no longer accepted
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.token_version == record.token_version => user,
+        _ => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Suspended accounts can't keep their sessions alive
+    if let Err(e) = check_account_usable(&user) {
+        return (jar, Err(e));
+    }
+
+    // The new token replaces the session created by the previous rotation
+    if auth::remove_family_sessions(&email, &record.family_id, state.session_store.clone())
+        .await
+        .is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let auth_cookie = match auth::generate_auth_cookie(
+        &user,
+        &client,
+        Some(record.family_id.clone()),
+        state.session_store.clone(),
+    ).await {
+        Ok(auth_cookie) => auth_cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    let refresh_cookie = match auth::generate_refresh_cookie(
+        &user,
+        record.family_id,
+        state.refresh_token_store.clone(),
+    ).await {
+        Ok(refresh_cookie) => refresh_cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    (jar.add(auth_cookie).add(refresh_cookie), Ok(StatusCode::OK))
+}
@@ -0,0 +1,67 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use serde::Deserialize;
+use crate::AppState;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode, TwoFACodeStoreError, TwoFAMethod, UserStoreError};
+
+use super::send_2fa_code;
+
+pub async fn resend_2fa(
+    State(state): State<AppState>,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let email = match Email::parse(&request.email) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let login_attempt_id = match LoginAttemptId::parse(request.login_attempt_id) {
+        Ok(login_attempt_id) => login_attempt_id,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    // Taken after the user store, the same order as everywhere else
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    let (expected_login_attempt_id, _) = match two_fa_code_store.get_code(&email).await {
+        Ok(code_tuple) => code_tuple,
+        Err(TwoFACodeStoreError::TooManyAttempts) => return Err(AuthAPIError::TooManyTwoFAAttempts),
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    if expected_login_attempt_id != login_attempt_id {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    // Authenticator app codes are never emailed
+    if user.two_fa_method != TwoFAMethod::Email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let two_fa_code = TwoFACode::default();
+
+    match two_fa_code_store.resend_code(&email, two_fa_code.clone()).await {
+        Ok(_) => (),
+        Err(TwoFACodeStoreError::TooManyResends) => return Err(AuthAPIError::TooManyRequests),
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    send_2fa_code(&state, &email, &two_fa_code).await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct Resend2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
@@ -0,0 +1,85 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::{Path, State};
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::{AuditEventType, AuthAPIError, SessionStoreError};
+use crate::utils::audit::record_audit_event;
+use crate::utils::auth::AuthenticatedUser;
+use crate::utils::client_info::ClientInfo;
+
+pub async fn get_sessions(State(state): State<AppState>, AuthenticatedUser { user, claims }: AuthenticatedUser)
+    -> Result<impl IntoResponse, AuthAPIError> {
+
+    let sessions = match state.session_store.read().await.get_sessions(&user.email).await {
+        Ok(sessions) => sessions,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: session.id == claims.jti,
+            id: session.id,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            user_agent: session.user_agent,
+            ip: session.ip,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(SessionsResponse { sessions })))
+}
+
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    AuthenticatedUser { user, claims }: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let mut session_store = state.session_store.write().await;
+
+    // Sessions of other users are reported as missing, so their ids can't be probed
+    let session = match session_store.get_session(&id).await {
+        Ok(session) if session.email == claims.sub => session,
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    match session_store.remove_session(&session.id).await {
+        Ok(_) => (),
+        Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    // Without this the revoked device could simply exchange its refresh token for a new session
+    if let Some(family_id) = session.refresh_family_id {
+        if state.refresh_token_store.write().await.revoke_family(&family_id).await.is_err() {
+            return Err(AuthAPIError::UnexpectedError);
+        }
+    }
+
+    drop(session_store);
+
+    record_audit_event(&state, &user.email, AuditEventType::SessionRevoked, &client).await;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: i64,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub current: bool,
+}
@@ -3,6 +3,10 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
 
 use crate::{app_state::AppState, domain::{AuthAPIError, Email, Password, User, UserStoreError}};
+use crate::utils::constants::ADMIN_EMAILS;
+use crate::utils::roles::grant_configured_admin_role;
+
+use super::{issue_recovery_codes, send_verification_email};
 
 pub async fn signup(
     State(state): State<AppState>,
@@ -19,27 +23,52 @@ pub async fn signup(
         Err(_) => return Err(AuthAPIError::InvalidCredentials),
     };
 
-    let user = User {
-        email: email,
-        password: password,
-        requires_2fa: request.requires_2fa,
-    };
-
-    let mut user_store = state.user_store.write().await;
+    let user = User::new(email.clone(), password, request.requires_2fa);
+    let requires_2fa = user.requires_2fa;
 
-    match user_store.add_user(user).await {
+    match state.user_store.write().await.add_user(user).await {
         Ok(_) => (),
         Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
         Err(_) => return Err(AuthAPIError::UnexpectedError),
     }
 
+    // A half set up account could never be used and would block signing up again, so it's removed
+    // if anything after this fails and the client can simply retry
+    let recovery_codes = match finish_signup(&state, &email, requires_2fa).await {
+        Ok(recovery_codes) => recovery_codes,
+        Err(e) => {
+            let _ = state.user_store.write().await.delete_user(&email).await;
+            return Err(e);
+        },
+    };
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
 
     Ok((StatusCode::CREATED, response))
 }
 
+// Returns the recovery codes of users that require 2FA. The email goes out last, so no link is sent
+// for an account that is removed again.
+async fn finish_signup(state: &AppState, email: &Email, requires_2fa: bool) -> Result<Option<Vec<String>>, AuthAPIError> {
+    // Shown once, so the user can still log in after losing their second factor
+    let recovery_codes = match requires_2fa {
+        true => Some(issue_recovery_codes(state, email).await?),
+        false => None,
+    };
+
+    if grant_configured_admin_role(&state.user_store, email, &ADMIN_EMAILS).await.is_err() {
+        return Err(AuthAPIError::UnexpectedError);
+    }
+
+    // The account can't be used until the link from this email is opened
+    send_verification_email(state, email).await?;
+
+    Ok(recovery_codes)
+}
+
 #[derive(Deserialize)]
 pub struct SignupRequest {
     pub email: String,
@@ -51,4 +80,6 @@ pub struct SignupRequest {
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+    #[serde(rename = "recoveryCodes", default, skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
 }
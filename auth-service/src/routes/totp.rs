@@ -0,0 +1,94 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::{AuthAPIError, Password, TwoFACode, TwoFAMethod, UserStoreError};
+use crate::utils::auth::AuthenticatedUser;
+use crate::utils::totp;
+use crate::utils::constants::{TOTP_ISSUER, TOTP_SKEW_STEPS};
+
+use super::{issue_recovery_codes, RecoveryCodesResponse};
+
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    AuthenticatedUser { user, .. }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    // Replacing an active secret would silently break the user's authenticator app
+    if user.requires_2fa && user.two_fa_method == TwoFAMethod::Totp {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    let secret = totp::generate_secret();
+
+    match state.user_store.write().await.set_totp_secret(&user.email, secret.clone()).await {
+        Ok(_) => (),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let response = Json(TotpEnrollmentResponse {
+        otpauth_uri: totp::provisioning_uri(&secret, &user.email, &TOTP_ISSUER),
+        secret,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    AuthenticatedUser { user, .. }: AuthenticatedUser,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let code = match TwoFACode::parse(request.code) {
+        Ok(code) => code,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let password = match Password::parse(&request.password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    // Confirming hands out recovery codes, which bypass the second factor, so a stolen session
+    // alone isn't enough to get them
+    match state.user_store.read().await.validate_user(&user.email, &password).await {
+        Ok(_) => (),
+        Err(UserStoreError::InvalidCredentials) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    // Enrollment has to be started first
+    let secret = user.totp_secret.as_ref().ok_or(AuthAPIError::InvalidCredentials)?;
+
+    // The first code proves the app was set up correctly before login starts depending on it
+    if !totp::verify_code(secret, &code, Utc::now().timestamp(), *TOTP_SKEW_STEPS) {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    match state.user_store.write().await.set_two_fa_method(&user.email, TwoFAMethod::Totp).await {
+        Ok(_) => (),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let response = Json(RecoveryCodesResponse {
+        recovery_codes: issue_recovery_codes(&state, &user.email).await?,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
@@ -1,13 +1,24 @@
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use axum::extract::State;
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
 use serde::Deserialize;
+use uuid::Uuid;
 use crate::AppState;
-use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode, UserStoreError};
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::{
+    AuditEventType, AuthAPIError, Email, LoginAttemptId, RecoveryCode, TwoFACode, TwoFACodeStoreError, TwoFAMethod,
+    User, UserStoreError,
+};
+use crate::utils::audit::record_audit_event;
+use crate::utils::auth::{generate_auth_cookie, generate_refresh_cookie};
+use crate::utils::constants::TOTP_SKEW_STEPS;
+use crate::utils::totp;
+use crate::utils::client_info::ClientInfo;
+use super::{check_account_usable, send_new_login_alert};
 
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    client: ClientInfo,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
 
@@ -21,35 +32,133 @@ pub async fn verify_2fa(
         Err(_) => return Err(AuthAPIError::InvalidCredentials),
     };
 
-    let two_fa_code = match TwoFACode::parse(request.two_fa_code) {
-        Ok(two_fa_code) => two_fa_code,
-        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    let second_factor = match TwoFACode::parse(request.two_fa_code.clone()) {
+        Ok(two_fa_code) => SecondFactor::Code(two_fa_code),
+        Err(_) => match RecoveryCode::parse(&request.two_fa_code) {
+            Ok(recovery_code) => SecondFactor::RecoveryCode(recovery_code),
+            Err(_) => return Err(AuthAPIError::InvalidCredentials),
+        },
     };
 
-    let mut two_fa_code_store =
-        state.two_fa_code_store.write().await;
-
     let user = match state.user_store.read().await.get_user(&email).await {
         Ok(user) => user,
         Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
         Err(_) => return Err(AuthAPIError::UnexpectedError),
     };
 
-    let code_tuple = two_fa_code_store.get_code(&email).await;
+    // The account may have been suspended since the code was sent
+    check_account_usable(&user)?;
 
-    match code_tuple {
-        Ok(code_tuple) if code_tuple == (login_attempt_id, two_fa_code) => {
+    // The code store is never locked while waiting for the user store, login takes them the other way round
+    let expected_code = get_expected_code(&state, &email, &login_attempt_id).await?;
 
-            two_fa_code_store.remove_code(&email).await.unwrap();
+    // Recovery codes are consumed under the user store lock, which is taken before the code store one like login does
+    let mut user_store = match second_factor {
+        SecondFactor::RecoveryCode(_) => Some(state.user_store.write().await),
+        SecondFactor::Code(_) => None,
+    };
 
-            let cookie = match generate_auth_cookie(&user.email) {
-                Ok(cookie) => cookie,
-                Err(_) => return Err(AuthAPIError::IncorrectCredentials),
-            };
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
-            Ok((jar.add(cookie), StatusCode::OK.into_response()))
+    // The code was checked without holding the lock, so make sure it hasn't been resent or used up since.
+    // Only the request that removes the code gets to log in.
+    match two_fa_code_store.get_code(&email).await {
+        Ok((current_login_attempt_id, current_code))
+            if current_login_attempt_id == login_attempt_id && current_code == expected_code => (),
+        Ok(_) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(TwoFACodeStoreError::TooManyAttempts) => return Err(AuthAPIError::TooManyTwoFAAttempts),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let code_accepted = match (&second_factor, user_store.as_mut()) {
+        (SecondFactor::Code(two_fa_code), _) => code_matches(&user, &expected_code, two_fa_code),
+        // Consumed once the login attempt is known to be current, so a stale or locked one doesn't burn it
+        (SecondFactor::RecoveryCode(recovery_code), Some(user_store)) => {
+            match user_store.use_recovery_code(&email, recovery_code).await {
+                Ok(_) => true,
+                Err(UserStoreError::InvalidCredentials) => false,
+                Err(_) => return Err(AuthAPIError::UnexpectedError),
+            }
         },
-        _ => Err(AuthAPIError::IncorrectCredentials),
+        (SecondFactor::RecoveryCode(_), None) => false,
+    };
+
+    drop(user_store);
+
+    if !code_accepted {
+        return match two_fa_code_store.record_failed_attempt(&email).await {
+            Err(TwoFACodeStoreError::TooManyAttempts) => Err(AuthAPIError::TooManyTwoFAAttempts),
+            Ok(_) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => Err(AuthAPIError::IncorrectCredentials),
+            Err(_) => Err(AuthAPIError::UnexpectedError),
+        };
+    }
+
+    if two_fa_code_store.remove_code(&email).await.is_err() {
+        return Err(AuthAPIError::UnexpectedError);
+    }
+
+    drop(two_fa_code_store);
+
+    send_new_login_alert(&state, &email, &client).await;
+
+    let refresh_family_id = Uuid::new_v4().to_string();
+
+    let cookie = match generate_auth_cookie(
+        &user,
+        &client,
+        Some(refresh_family_id.clone()),
+        state.session_store.clone(),
+    ).await {
+        Ok(cookie) => cookie,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    let refresh_cookie = match generate_refresh_cookie(
+        &user,
+        refresh_family_id,
+        state.refresh_token_store.clone(),
+    ).await {
+        Ok(refresh_cookie) => refresh_cookie,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    record_audit_event(&state, &email, AuditEventType::Login, &client).await;
+
+    Ok((jar.add(cookie).add(refresh_cookie), StatusCode::OK.into_response()))
+}
+
+async fn get_expected_code(
+    state: &AppState,
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+) -> Result<TwoFACode, AuthAPIError> {
+    let (expected_login_attempt_id, expected_code) = match state.two_fa_code_store.read().await.get_code(email).await {
+        Ok(code_tuple) => code_tuple,
+        Err(TwoFACodeStoreError::TooManyAttempts) => return Err(AuthAPIError::TooManyTwoFAAttempts),
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    // Only guesses made with the right login attempt id count, so others can't lock the user out
+    if &expected_login_attempt_id != login_attempt_id {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    Ok(expected_code)
+}
+
+// The `2FACode` field also accepts a recovery code in place of the usual one
+enum SecondFactor {
+    Code(TwoFACode),
+    RecoveryCode(RecoveryCode),
+}
+
+// Emailed codes are compared with the stored one, authenticator app codes are derived from the TOTP secret
+fn code_matches(user: &User, expected_code: &TwoFACode, code: &TwoFACode) -> bool {
+    match (user.two_fa_method, &user.totp_secret) {
+        (TwoFAMethod::Totp, Some(secret)) =>
+            totp::verify_code(secret, code, Utc::now().timestamp(), *TOTP_SKEW_STEPS),
+        (TwoFAMethod::Totp, None) => false,
+        (TwoFAMethod::Email, _) => expected_code == code,
     }
 }
 
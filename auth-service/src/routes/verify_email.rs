@@ -0,0 +1,98 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::{Query, State};
+use serde::{Deserialize, Serialize};
+use crate::AppState;
+use crate::domain::{
+    AccountStatus, AuthAPIError, Email, EmailVerificationToken, EmailVerificationTokenStoreError, UserStoreError,
+};
+use crate::utils::auth::EMAIL_VERIFICATION_TOKEN_TTL_SECONDS;
+use crate::utils::constants::EMAIL_VERIFICATION_URL;
+use crate::utils::email_templates::{queue_email, EmailVerificationEmail};
+
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let token = match EmailVerificationToken::parse(query.token) {
+        Ok(token) => token,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    let email = match state.email_verification_token_store.write().await.consume_token(&token).await {
+        Ok(email) => email,
+        Err(EmailVerificationTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    match state.user_store.write().await.mark_email_verified(&email).await {
+        Ok(_) => (),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let email = match Email::parse(&request.email) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let response = Json(VerifyEmailResponse {
+        message: "If the account awaits verification, a new link has been sent".to_owned(),
+    });
+
+    // Unknown and already verified emails get the same response as the others
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.status == AccountStatus::PendingVerification => (),
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    send_verification_email(&state, &email).await?;
+
+    Ok((StatusCode::OK, response))
+}
+
+// Issue a new verification token and email the link to the user
+pub(crate) async fn send_verification_email(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    let token = EmailVerificationToken::default();
+
+    match state.email_verification_token_store.write().await.add_token(token.clone(), email.clone()).await {
+        Ok(_) => (),
+        Err(EmailVerificationTokenStoreError::TooManyRequests) => return Err(AuthAPIError::TooManyRequests),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let url = format!("{}?token={}", EMAIL_VERIFICATION_URL.as_str(), token.as_ref());
+
+    queue_email(state, email, &EmailVerificationEmail {
+        url: &url,
+        expires_in_hours: EMAIL_VERIFICATION_TOKEN_TTL_SECONDS / 3600,
+    }).await
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationEmailRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
@@ -10,9 +10,19 @@ pub async fn verify_token(
     Json(request): Json<VerifyTokenRequest>
 ) -> Result<impl IntoResponse, AuthAPIError> {
 
-    match auth::validate_token(&request.token, state.banned_token_store.clone()).await {
-        Ok(_) => (),
+    let claims = match auth::validate_token(
+        &request.token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    ).await {
+        Ok(claims) => claims,
         Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    // Callers making authorization decisions can ask for the decoded claims
+    if request.include_claims {
+        return Ok((StatusCode::OK, Json(claims)).into_response());
     }
 
     Ok(StatusCode::OK.into_response())
@@ -20,5 +30,7 @@ pub async fn verify_token(
 
 #[derive(Deserialize)]
 pub struct VerifyTokenRequest {
-    pub token: String
+    pub token: String,
+    #[serde(rename = "includeClaims", default)]
+    pub include_claims: bool,
 }
@@ -0,0 +1,369 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::AppState;
+use crate::domain::{
+    AuditEventType, AuthAPIError, CredentialStoreError, Email, UserStoreError, WebAuthnCeremony, WebAuthnChallenge,
+    WebAuthnCredential,
+};
+use crate::utils::audit::record_audit_event;
+use crate::utils::auth::{
+    generate_auth_cookie, generate_refresh_cookie, AuthenticatedUser, WEBAUTHN_CHALLENGE_TTL_SECONDS,
+};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::WEBAUTHN_RELYING_PARTY;
+use crate::utils::webauthn::{self, ClientData, SUPPORTED_ALGORITHMS};
+
+use super::{check_account_usable, send_new_login_alert};
+
+const PUBLIC_KEY_CREDENTIAL_TYPE: &str = "public-key";
+
+// Passkeys are added to an account the user is already logged in to
+pub async fn begin_webauthn_registration(
+    State(state): State<AppState>,
+    AuthenticatedUser { user, .. }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    // Authenticators refuse to create a second credential for the same account
+    let exclude_credentials = match state.credential_store.read().await.get_credentials(&user.email).await {
+        Ok(credentials) => credentials.into_iter().map(CredentialDescriptor::from).collect(),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let challenge = add_challenge(&state, WebAuthnCeremony::Registration, Some(&user.email)).await?;
+
+    let response = Json(RegistrationOptionsResponse {
+        public_key: CredentialCreationOptions {
+            challenge,
+            rp: RelyingPartyEntity {
+                id: WEBAUTHN_RELYING_PARTY.id.clone(),
+                name: WEBAUTHN_RELYING_PARTY.name.clone(),
+            },
+            user: UserEntity {
+                id: webauthn::user_handle(&user.email),
+                name: user.email.to_string(),
+                display_name: user.email.to_string(),
+            },
+            pub_key_cred_params: SUPPORTED_ALGORITHMS
+                .iter()
+                .map(|alg| CredentialParameters { credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(), alg: *alg })
+                .collect(),
+            timeout: timeout_millis(),
+            attestation: "none".to_owned(),
+            exclude_credentials,
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred".to_owned(),
+                user_verification: "required".to_owned(),
+            },
+        },
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+pub async fn finish_webauthn_registration(
+    State(state): State<AppState>,
+    AuthenticatedUser { user, .. }: AuthenticatedUser,
+    Json(request): Json<RegistrationCredential>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    let client_data = ClientData::parse(&request.response.client_data_json)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let challenge = consume_challenge(&state, client_data.challenge()).await?;
+
+    // The challenge has to come from a registration started by the same user
+    if challenge.ceremony != WebAuthnCeremony::Registration || challenge.email.as_deref() != Some(user.email.as_ref()) {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let registered = match webauthn::verify_registration(
+        &WEBAUTHN_RELYING_PARTY,
+        &client_data,
+        &request.response.attestation_object,
+    ) {
+        Ok(registered) if registered.id == request.id => registered,
+        _ => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    let credential = WebAuthnCredential {
+        id: registered.id,
+        email: user.email.to_string(),
+        public_key: registered.public_key,
+        sign_count: registered.sign_count,
+        created_at: Utc::now().timestamp(),
+    };
+
+    match state.credential_store.write().await.add_credential(credential).await {
+        Ok(_) => Ok(StatusCode::CREATED),
+        Err(CredentialStoreError::CredentialAlreadyExists) => Err(AuthAPIError::CredentialAlreadyExists),
+        Err(_) => Err(AuthAPIError::UnexpectedError),
+    }
+}
+
+pub async fn begin_webauthn_login(
+    State(state): State<AppState>,
+    Json(request): Json<BeginWebAuthnLoginRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+
+    // Without an email the browser offers the passkeys it has stored for the site
+    let email = match request.email {
+        Some(email) => Some(Email::parse(&email).map_err(|_| AuthAPIError::InvalidCredentials)?),
+        None => None,
+    };
+
+    // Unknown emails get an empty list, so the response doesn't reveal which accounts exist
+    let allow_credentials = match &email {
+        Some(email) => match state.credential_store.read().await.get_credentials(email).await {
+            Ok(credentials) => credentials.into_iter().map(CredentialDescriptor::from).collect(),
+            Err(_) => return Err(AuthAPIError::UnexpectedError),
+        },
+        None => Vec::new(),
+    };
+
+    let challenge = add_challenge(&state, WebAuthnCeremony::Authentication, email.as_ref()).await?;
+
+    let response = Json(LoginOptionsResponse {
+        public_key: CredentialRequestOptions {
+            challenge,
+            rp_id: WEBAUTHN_RELYING_PARTY.id.clone(),
+            allow_credentials,
+            timeout: timeout_millis(),
+            user_verification: "required".to_owned(),
+        },
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+pub async fn finish_webauthn_login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    jar: CookieJar,
+    Json(request): Json<AuthenticationCredential>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+
+    let client_data = ClientData::parse(&request.response.client_data_json)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let challenge = consume_challenge(&state, client_data.challenge()).await?;
+
+    if challenge.ceremony != WebAuthnCeremony::Authentication {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let credential = match state.credential_store.read().await.get_credential(&request.id).await {
+        Ok(credential) => credential,
+        Err(CredentialStoreError::CredentialNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    // A login started for one account can't be finished with another account's passkey
+    if challenge.email.as_ref().is_some_and(|email| *email != credential.email) {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let sign_count = match webauthn::verify_assertion(
+        &WEBAUTHN_RELYING_PARTY,
+        &client_data,
+        &request.response.authenticator_data,
+        &request.response.signature,
+        &credential.public_key,
+        credential.sign_count,
+    ) {
+        Ok(sign_count) => sign_count,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    if state.credential_store.write().await.update_sign_count(&credential.id, sign_count).await.is_err() {
+        return Err(AuthAPIError::UnexpectedError);
+    }
+
+    let email = Email::parse(&credential.email).map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    check_account_usable(&user)?;
+
+    send_new_login_alert(&state, &email, &client).await;
+
+    // The passkey proves possession of the authenticator, so no second factor is asked for
+    let refresh_family_id = Uuid::new_v4().to_string();
+
+    let cookie = match generate_auth_cookie(
+        &user,
+        &client,
+        Some(refresh_family_id.clone()),
+        state.session_store.clone(),
+    ).await {
+        Ok(cookie) => cookie,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let refresh_cookie = match generate_refresh_cookie(
+        &user,
+        refresh_family_id,
+        state.refresh_token_store.clone(),
+    ).await {
+        Ok(refresh_cookie) => refresh_cookie,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    record_audit_event(&state, &email, AuditEventType::Login, &client).await;
+
+    Ok((jar.add(cookie).add(refresh_cookie), StatusCode::OK))
+}
+
+async fn add_challenge(
+    state: &AppState,
+    ceremony: WebAuthnCeremony,
+    email: Option<&Email>,
+) -> Result<String, AuthAPIError> {
+    let challenge = WebAuthnChallenge {
+        challenge: webauthn::generate_challenge(),
+        ceremony,
+        email: email.map(|email| email.to_string()),
+    };
+
+    match state.webauthn_challenge_store.write().await.add_challenge(challenge.clone()).await {
+        Ok(_) => Ok(challenge.challenge),
+        Err(_) => Err(AuthAPIError::UnexpectedError),
+    }
+}
+
+async fn consume_challenge(state: &AppState, challenge: &str) -> Result<WebAuthnChallenge, AuthAPIError> {
+    match state.webauthn_challenge_store.write().await.consume_challenge(challenge).await {
+        Ok(challenge) => Ok(challenge),
+        Err(_) => Err(AuthAPIError::IncorrectCredentials),
+    }
+}
+
+fn timeout_millis() -> u64 {
+    WEBAUTHN_CHALLENGE_TTL_SECONDS as u64 * 1000
+}
+
+// Options passed to `navigator.credentials.create()`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrationOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: CredentialCreationOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialCreationOptions {
+    pub challenge: String,
+    pub rp: RelyingPartyEntity,
+    pub user: UserEntity,
+    #[serde(rename = "pubKeyCredParams")]
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: u64,
+    pub attestation: String,
+    #[serde(rename = "excludeCredentials")]
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    #[serde(rename = "authenticatorSelection")]
+    pub authenticator_selection: AuthenticatorSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelyingPartyEntity {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserEntity {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthenticatorSelection {
+    #[serde(rename = "residentKey")]
+    pub resident_key: String,
+    #[serde(rename = "userVerification")]
+    pub user_verification: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+impl From<WebAuthnCredential> for CredentialDescriptor {
+    fn from(credential: WebAuthnCredential) -> Self {
+        CredentialDescriptor { credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(), id: credential.id }
+    }
+}
+
+// Options passed to `navigator.credentials.get()`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: CredentialRequestOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialRequestOptions {
+    pub challenge: String,
+    #[serde(rename = "rpId")]
+    pub rp_id: String,
+    #[serde(rename = "allowCredentials")]
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub timeout: u64,
+    #[serde(rename = "userVerification")]
+    pub user_verification: String,
+}
+
+#[derive(Deserialize)]
+pub struct BeginWebAuthnLoginRequest {
+    pub email: Option<String>,
+}
+
+// PublicKeyCredential returned by `navigator.credentials.create()`, with binary fields base64url encoded
+#[derive(Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+// PublicKeyCredential returned by `navigator.credentials.get()`, with binary fields base64url encoded
+#[derive(Deserialize)]
+pub struct AuthenticationCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
@@ -0,0 +1,72 @@
+use crate::domain::{AuditEvent, AuditLogStore, AuditLogStoreError, Email};
+
+#[derive(Default)]
+pub struct HashmapAuditLogStore {
+    events: Vec<AuditEvent>,
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for HashmapAuditLogStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    async fn get_events(&self, email: &Email) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        let mut events: Vec<AuditEvent> = self.events
+            .iter()
+            .filter(|event| event.email == email.as_ref())
+            .cloned()
+            .collect();
+
+        events.sort_by_key(|event| event.created_at);
+
+        Ok(events)
+    }
+
+    async fn delete_events(&mut self, email: &Email) -> Result<(), AuditLogStoreError> {
+        self.events.retain(|event| event.email != email.as_ref());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::AuditEventType;
+
+    use super::*;
+
+    fn audit_event(email: &str, event_type: AuditEventType, now: i64) -> AuditEvent {
+        AuditEvent::new(&Email::parse(email).unwrap(), event_type, Some("10.0.0.1".to_owned()), None, now)
+    }
+
+    #[tokio::test]
+    async fn test_get_events() {
+        let mut store = HashmapAuditLogStore::default();
+        let password_changed = audit_event("user@example.com", AuditEventType::PasswordChanged, 200);
+        let login = audit_event("user@example.com", AuditEventType::Login, 100);
+
+        store.add_event(password_changed.clone()).await.unwrap();
+        store.add_event(login.clone()).await.unwrap();
+        store.add_event(audit_event("another@example.com", AuditEventType::Login, 100)).await.unwrap();
+
+        assert_eq!(
+            store.get_events(&Email::parse("user@example.com").unwrap()).await,
+            Ok(vec![login, password_changed])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_events() {
+        let mut store = HashmapAuditLogStore::default();
+        let other = audit_event("another@example.com", AuditEventType::Login, 100);
+
+        store.add_event(audit_event("user@example.com", AuditEventType::Login, 100)).await.unwrap();
+        store.add_event(other.clone()).await.unwrap();
+
+        assert_eq!(store.delete_events(&Email::parse("user@example.com").unwrap()).await, Ok(()));
+
+        assert_eq!(store.get_events(&Email::parse("user@example.com").unwrap()).await, Ok(vec![]));
+        assert_eq!(store.get_events(&Email::parse("another@example.com").unwrap()).await, Ok(vec![other]));
+    }
+}
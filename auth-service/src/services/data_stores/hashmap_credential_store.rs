@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::domain::{CredentialStore, CredentialStoreError, Email, WebAuthnCredential};
+
+#[derive(Default)]
+pub struct HashmapCredentialStore {
+    credentials: HashMap<String, WebAuthnCredential>,
+}
+
+#[async_trait::async_trait]
+impl CredentialStore for HashmapCredentialStore {
+    async fn add_credential(&mut self, credential: WebAuthnCredential) -> Result<(), CredentialStoreError> {
+        match self.credentials.get(&credential.id) {
+            Some(_) => Err(CredentialStoreError::CredentialAlreadyExists),
+            None => {
+                self.credentials.insert(credential.id.clone(), credential);
+                Ok(())
+            }
+        }
+    }
+
+    async fn get_credential(&self, id: &str) -> Result<WebAuthnCredential, CredentialStoreError> {
+        self.credentials
+            .get(id)
+            .cloned()
+            .ok_or(CredentialStoreError::CredentialNotFound)
+    }
+
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, CredentialStoreError> {
+        let mut credentials: Vec<_> = self.credentials
+            .values()
+            .filter(|credential| credential.email == email.as_ref())
+            .cloned()
+            .collect();
+
+        credentials.sort_by_key(|credential| credential.created_at);
+
+        Ok(credentials)
+    }
+
+    async fn update_sign_count(&mut self, id: &str, sign_count: u32) -> Result<(), CredentialStoreError> {
+        match self.credentials.get_mut(id) {
+            Some(credential) => {
+                credential.sign_count = sign_count;
+                Ok(())
+            },
+            None => Err(CredentialStoreError::CredentialNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credential(id: &str, email: &str, created_at: i64) -> WebAuthnCredential {
+        WebAuthnCredential {
+            id: id.to_owned(),
+            email: email.to_owned(),
+            public_key: vec![1, 2, 3],
+            sign_count: 0,
+            created_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_credential() {
+        let mut store = HashmapCredentialStore::default();
+
+        assert_eq!(store.add_credential(credential("a", "user@example.com", 1)).await, Ok(()));
+
+        assert_eq!(
+            store.add_credential(credential("a", "another@example.com", 2)).await,
+            Err(CredentialStoreError::CredentialAlreadyExists)
+        );
+
+        assert_eq!(store.get_credential("a").await, Ok(credential("a", "user@example.com", 1)));
+
+        assert_eq!(
+            store.get_credential("b").await,
+            Err(CredentialStoreError::CredentialNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_credentials() {
+        let mut store = HashmapCredentialStore::default();
+
+        store.add_credential(credential("b", "user@example.com", 2)).await.unwrap();
+        store.add_credential(credential("a", "user@example.com", 1)).await.unwrap();
+        store.add_credential(credential("c", "another@example.com", 3)).await.unwrap();
+
+        let ids: Vec<_> = store
+            .get_credentials(&Email::parse("user@example.com").unwrap())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|credential| credential.id)
+            .collect();
+
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashmapCredentialStore::default();
+
+        store.add_credential(credential("a", "user@example.com", 1)).await.unwrap();
+
+        assert_eq!(store.update_sign_count("a", 5).await, Ok(()));
+
+        assert_eq!(store.get_credential("a").await.unwrap().sign_count, 5);
+
+        assert_eq!(
+            store.update_sign_count("b", 5).await,
+            Err(CredentialStoreError::CredentialNotFound)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use crate::domain::{Email, EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxEmailStatus};
+
+#[derive(Default)]
+pub struct HashmapEmailOutboxStore {
+    emails: HashMap<String, OutboxEmail>,
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for HashmapEmailOutboxStore {
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        self.emails.entry(email.id.clone()).or_insert(email);
+        Ok(())
+    }
+
+    async fn claim_due(&mut self, now: i64, lease_until: i64, limit: usize)
+        -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let mut due: Vec<&mut OutboxEmail> = self.emails
+            .values_mut()
+            .filter(|email| email.status == OutboxEmailStatus::Pending && email.next_attempt_at <= now)
+            .collect();
+
+        due.sort_by_key(|email| email.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit)
+            .map(|email| {
+                email.attempts += 1;
+                email.next_attempt_at = lease_until;
+                email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&mut self, id: &str) -> Result<(), EmailOutboxStoreError> {
+        let email = self.emails.get_mut(id).ok_or(EmailOutboxStoreError::EmailNotFound)?;
+
+        email.status = OutboxEmailStatus::Sent;
+        email.last_error = None;
+
+        Ok(())
+    }
+
+    async fn mark_failed(&mut self, id: &str, error: &str, retry_at: Option<i64>) -> Result<(), EmailOutboxStoreError> {
+        let email = self.emails.get_mut(id).ok_or(EmailOutboxStoreError::EmailNotFound)?;
+
+        email.last_error = Some(error.to_owned());
+        match retry_at {
+            Some(retry_at) => email.next_attempt_at = retry_at,
+            None => email.status = OutboxEmailStatus::Dead,
+        }
+
+        Ok(())
+    }
+
+    async fn get_emails(&self, recipient: &Email) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let mut emails: Vec<OutboxEmail> = self.emails
+            .values()
+            .filter(|email| email.recipient == recipient.as_ref())
+            .cloned()
+            .collect();
+
+        emails.sort_by_key(|email| email.created_at);
+
+        Ok(emails)
+    }
+
+    async fn delete_emails(&mut self, recipient: &Email) -> Result<(), EmailOutboxStoreError> {
+        self.emails.retain(|_, email| email.recipient != recipient.as_ref());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::EmailMessage;
+
+    use super::*;
+
+    fn outbox_email(now: i64) -> OutboxEmail {
+        let message = EmailMessage {
+            subject: "Subject".to_owned(),
+            html: "<p>Body</p>".to_owned(),
+            text: "Body".to_owned(),
+        };
+
+        OutboxEmail::new(&Email::parse("user@example.com").unwrap(), message, now)
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_is_idempotent() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = outbox_email(100);
+
+        assert_eq!(store.enqueue(email.clone()).await, Ok(()));
+        assert_eq!(store.enqueue(email.clone()).await, Ok(()));
+
+        assert_eq!(store.get_emails(&Email::parse("user@example.com").unwrap()).await, Ok(vec![email]));
+    }
+
+    #[tokio::test]
+    async fn test_claim_due() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = outbox_email(100);
+
+        store.enqueue(email.clone()).await.unwrap();
+        store.enqueue(outbox_email(200)).await.unwrap();
+
+        let claimed = store.claim_due(150, 300, 10).await.unwrap();
+
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, email.id);
+        assert_eq!(claimed[0].attempts, 1);
+
+        // Leased emails aren't handed out again until the lease ends
+        let claimed = store.claim_due(250, 400, 10).await.unwrap();
+
+        assert_eq!(claimed.len(), 1);
+        assert_ne!(claimed[0].id, email.id);
+
+        let claimed = store.claim_due(300, 500, 10).await.unwrap();
+
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, email.id);
+        assert_eq!(claimed[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_mark_sent_and_failed() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let sent = outbox_email(100);
+        let dead = outbox_email(100);
+
+        store.enqueue(sent.clone()).await.unwrap();
+        store.enqueue(dead.clone()).await.unwrap();
+
+        assert_eq!(store.mark_sent(&sent.id).await, Ok(()));
+        assert_eq!(store.mark_failed(&dead.id, "Rejected", None).await, Ok(()));
+        assert_eq!(store.mark_sent("unknown").await, Err(EmailOutboxStoreError::EmailNotFound));
+
+        // Neither is ever handed out again
+        assert_eq!(store.claim_due(i64::MAX, i64::MAX, 10).await, Ok(vec![]));
+
+        let emails = store.get_emails(&Email::parse("user@example.com").unwrap()).await.unwrap();
+        let dead = emails.iter().find(|email| email.id == dead.id).unwrap();
+
+        assert_eq!(dead.status, OutboxEmailStatus::Dead);
+        assert_eq!(dead.last_error.as_deref(), Some("Rejected"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_emails() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let other = OutboxEmail::new(&Email::parse("another@example.com").unwrap(), outbox_email(100).message, 100);
+
+        store.enqueue(outbox_email(100)).await.unwrap();
+        store.enqueue(other.clone()).await.unwrap();
+
+        assert_eq!(store.delete_emails(&Email::parse("user@example.com").unwrap()).await, Ok(()));
+
+        assert_eq!(store.get_emails(&Email::parse("user@example.com").unwrap()).await, Ok(vec![]));
+        assert_eq!(store.get_emails(&Email::parse("another@example.com").unwrap()).await, Ok(vec![other]));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{
+    Email, EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError,
+};
+use crate::utils::auth::{EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS};
+
+#[derive(Default)]
+pub struct HashmapEmailVerificationTokenStore {
+    // Email the token was issued for and the time it expires at
+    tokens: HashMap<EmailVerificationToken, (Email, i64)>,
+    // Time the last token was issued at for every email
+    issued_at: HashMap<Email, i64>,
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for HashmapEmailVerificationTokenStore {
+    async fn add_token(&mut self, token: EmailVerificationToken, email: Email)
+        -> Result<(), EmailVerificationTokenStoreError> {
+        let now = Utc::now().timestamp();
+
+        if let Some(issued_at) = self.issued_at.get(&email) {
+            if now < issued_at + EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS {
+                return Err(EmailVerificationTokenStoreError::TooManyRequests);
+            }
+        }
+
+        self.issued_at.insert(email.clone(), now);
+        self.tokens.insert(token, (email, now + EMAIL_VERIFICATION_TOKEN_TTL_SECONDS));
+        Ok(())
+    }
+
+    async fn consume_token(&mut self, token: &EmailVerificationToken)
+        -> Result<Email, EmailVerificationTokenStoreError> {
+        self.tokens
+            .remove(token)
+            .filter(|(_, expires_at)| *expires_at > Utc::now().timestamp())
+            .map(|(email, _)| email)
+            .ok_or(EmailVerificationTokenStoreError::TokenNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let token = EmailVerificationToken::default();
+        let email = Email::parse("user@example.com").unwrap();
+
+        assert_eq!(store.add_token(token.clone(), email.clone()).await, Ok(()));
+
+        assert_eq!(store.consume_token(&token).await, Ok(email));
+
+        assert_eq!(
+            store.consume_token(&token).await,
+            Err(EmailVerificationTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_token_too_soon() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse("user@example.com").unwrap();
+
+        store.add_token(EmailVerificationToken::default(), email.clone()).await.unwrap();
+
+        assert_eq!(
+            store.add_token(EmailVerificationToken::default(), email.clone()).await,
+            Err(EmailVerificationTokenStoreError::TooManyRequests)
+        );
+
+        store.issued_at.insert(email.clone(), Utc::now().timestamp() - EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS);
+
+        assert_eq!(store.add_token(EmailVerificationToken::default(), email).await, Ok(()));
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{LoginAttemptKey, LoginAttemptStore, LoginAttemptStoreError};
+use crate::utils::auth::{login_lockout_seconds, LOGIN_FAILURE_TTL_SECONDS};
+
+#[derive(Default)]
+pub struct HashmapLoginAttemptStore {
+    attempts: HashMap<LoginAttemptKey, LoginAttempts>,
+}
+
+struct LoginAttempts {
+    failures: u32,
+    // Time the failures are forgotten at
+    expires_at: i64,
+    // Time the lockout ends at
+    locked_until: i64,
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for HashmapLoginAttemptStore {
+    async fn get_lockout(&self, key: &LoginAttemptKey) -> Result<Option<i64>, LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+
+        Ok(self.attempts
+            .get(key)
+            .filter(|attempts| attempts.locked_until > now)
+            .map(|attempts| attempts.locked_until - now))
+    }
+
+    async fn record_failure(&mut self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+
+        let attempts = self.attempts.entry(key.clone()).or_insert(LoginAttempts {
+            failures: 0,
+            expires_at: now,
+            locked_until: now,
+        });
+
+        if attempts.expires_at <= now {
+            attempts.failures = 0;
+        }
+
+        attempts.failures += 1;
+        attempts.expires_at = now + LOGIN_FAILURE_TTL_SECONDS;
+
+        let lockout = login_lockout_seconds(key, attempts.failures);
+
+        if lockout > 0 {
+            attempts.locked_until = now + lockout;
+        }
+
+        Ok(())
+    }
+
+    async fn reset(&mut self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        self.attempts.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::Email;
+    use crate::utils::auth::{LOGIN_LOCKOUT_BASE_SECONDS, MAX_LOGIN_FAILURES_PER_EMAIL};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_failure() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let key = LoginAttemptKey::Email(Email::parse("user@example.com").unwrap());
+
+        for _ in 1..MAX_LOGIN_FAILURES_PER_EMAIL {
+            assert_eq!(store.record_failure(&key).await, Ok(()));
+        }
+
+        assert_eq!(store.get_lockout(&key).await, Ok(None));
+
+        assert_eq!(store.record_failure(&key).await, Ok(()));
+
+        let lockout = store.get_lockout(&key).await.unwrap().unwrap();
+
+        assert!(lockout > 0 && lockout <= LOGIN_LOCKOUT_BASE_SECONDS);
+
+        // Every further failure doubles the lockout
+        assert_eq!(store.record_failure(&key).await, Ok(()));
+
+        let lockout = store.get_lockout(&key).await.unwrap().unwrap();
+
+        assert!(lockout > LOGIN_LOCKOUT_BASE_SECONDS && lockout <= LOGIN_LOCKOUT_BASE_SECONDS * 2);
+    }
+
+    #[tokio::test]
+    async fn test_reset() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let key = LoginAttemptKey::Ip("127.0.0.1".to_owned());
+
+        for _ in 0..MAX_LOGIN_FAILURES_PER_EMAIL {
+            assert_eq!(store.record_failure(&key).await, Ok(()));
+        }
+
+        assert_eq!(store.reset(&key).await, Ok(()));
+        assert_eq!(store.get_lockout(&key).await, Ok(None));
+
+        // The count starts over as well
+        assert_eq!(store.record_failure(&key).await, Ok(()));
+        assert_eq!(store.get_lockout(&key).await, Ok(None));
+    }
+}
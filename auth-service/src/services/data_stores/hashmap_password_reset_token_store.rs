@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{Email, PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError};
+use crate::utils::auth::PASSWORD_RESET_TOKEN_TTL_SECONDS;
+
+#[derive(Default)]
+pub struct HashmapPasswordResetTokenStore {
+    // Email the token was issued for and the time it expires at
+    tokens: HashMap<PasswordResetToken, (Email, i64)>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(&mut self, token: PasswordResetToken, email: Email)
+        -> Result<(), PasswordResetTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + PASSWORD_RESET_TOKEN_TTL_SECONDS;
+        self.tokens.insert(token, (email, expires_at));
+        Ok(())
+    }
+
+    async fn consume_token(&mut self, token: &PasswordResetToken)
+        -> Result<Email, PasswordResetTokenStoreError> {
+        self.tokens
+            .remove(token)
+            .filter(|(_, expires_at)| *expires_at > Utc::now().timestamp())
+            .map(|(email, _)| email)
+            .ok_or(PasswordResetTokenStoreError::TokenNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let token = PasswordResetToken::default();
+        let email = Email::parse("user@example.com").unwrap();
+
+        assert_eq!(store.add_token(token.clone(), email.clone()).await, Ok(()));
+
+        assert_eq!(store.consume_token(&token).await, Ok(email));
+
+        assert_eq!(
+            store.consume_token(&token).await,
+            Err(PasswordResetTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consume_expired_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let token = PasswordResetToken::default();
+        let email = Email::parse("user@example.com").unwrap();
+
+        store.tokens.insert(token.clone(), (email, Utc::now().timestamp() - 1));
+
+        assert_eq!(
+            store.consume_token(&token).await,
+            Err(PasswordResetTokenStoreError::TokenNotFound)
+        );
+    }
+}
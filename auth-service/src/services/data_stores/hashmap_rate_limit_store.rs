@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{RateLimit, RateLimitStatus, RateLimitStore, RateLimitStoreError, TokenBucket};
+
+// Number of buckets kept before full ones are dropped
+const MAX_BUCKETS: usize = 10_000;
+
+#[derive(Default)]
+pub struct HashmapRateLimitStore {
+    // Bucket and the time it is full again
+    buckets: HashMap<String, (TokenBucket, i64)>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn take_token(&mut self, key: &str, limit: &RateLimit) -> Result<RateLimitStatus, RateLimitStoreError> {
+        let now_ms = Utc::now().timestamp_millis();
+
+        // A full bucket behaves like a missing one, so it can go
+        if self.buckets.len() >= MAX_BUCKETS {
+            self.buckets.retain(|_, (_, full_at_ms)| *full_at_ms > now_ms);
+        }
+
+        let (bucket, full_at_ms) = self.buckets
+            .entry(key.to_owned())
+            .or_insert_with(|| (TokenBucket::new(limit, now_ms), now_ms));
+
+        let status = bucket.take(limit, now_ms);
+        *full_at_ms = bucket.full_at_ms(limit);
+
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_token() {
+        let mut store = HashmapRateLimitStore::default();
+        let limit = RateLimit { capacity: 2, period_seconds: 60 };
+
+        assert!(store.take_token("127.0.0.1:/login", &limit).await.unwrap().allowed);
+        assert!(store.take_token("127.0.0.1:/login", &limit).await.unwrap().allowed);
+
+        let status = store.take_token("127.0.0.1:/login", &limit).await.unwrap();
+
+        assert!(!status.allowed);
+        assert!(status.retry_after_seconds > 0);
+
+        // Buckets are kept apart per key
+        assert!(store.take_token("127.0.0.1:/signup", &limit).await.unwrap().allowed);
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::{RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError};
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<RefreshToken, RefreshTokenRecord>,
+    revoked_families: HashSet<String>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(&mut self, token: RefreshToken, record: RefreshTokenRecord)
+        -> Result<(), RefreshTokenStoreError> {
+        self.tokens.insert(token, record);
+        Ok(())
+    }
+
+    async fn consume_token(&mut self, token: &RefreshToken)
+        -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let record = self.tokens
+            .get_mut(token)
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        if self.revoked_families.contains(&record.family_id) {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        if record.used {
+            return Err(RefreshTokenStoreError::TokenReused(record.clone()));
+        }
+
+        record.used = true;
+
+        Ok(record.clone())
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        self.revoked_families.insert(family_id.to_owned());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{Email, Password, User};
+
+    use super::*;
+
+    fn record() -> RefreshTokenRecord {
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false,
+        );
+
+        RefreshTokenRecord::new(&user, "family".to_owned())
+    }
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        assert_eq!(store.add_token(token.clone(), record()).await, Ok(()));
+
+        let consumed = store.consume_token(&token).await.unwrap();
+
+        assert_eq!(consumed.email, "user@example.com");
+        assert_eq!(consumed.family_id, "family");
+
+        assert_eq!(
+            store.consume_token(&RefreshToken::default()).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consume_token_twice() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        store.add_token(token.clone(), record()).await.unwrap();
+        store.consume_token(&token).await.unwrap();
+
+        assert_eq!(
+            store.consume_token(&token).await,
+            Err(RefreshTokenStoreError::TokenReused(RefreshTokenRecord { used: true, ..record() }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        store.add_token(token.clone(), record()).await.unwrap();
+        store.revoke_family("family").await.unwrap();
+
+        assert_eq!(
+            store.consume_token(&token).await,
+            Err(RefreshTokenStoreError::FamilyRevoked)
+        );
+    }
+}
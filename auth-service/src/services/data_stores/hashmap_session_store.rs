@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{Email, Session, SessionStore, SessionStoreError};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(id)
+            .filter(|session| session.expires_at > Utc::now().timestamp())
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let now = Utc::now().timestamp();
+
+        let mut sessions: Vec<Session> = self.sessions
+            .values()
+            .filter(|session| session.email == email.as_ref() && session.expires_at > now)
+            .cloned()
+            .collect();
+
+        sessions.sort_by_key(|session| session.created_at);
+
+        Ok(sessions)
+    }
+
+    async fn remove_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        self.sessions
+            .remove(id)
+            .map(|_| ())
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, email: &str, expires_at: i64) -> Session {
+        Session {
+            id: id.to_owned(),
+            email: email.to_owned(),
+            created_at: Utc::now().timestamp(),
+            expires_at,
+            user_agent: Some("test-agent".to_owned()),
+            ip: Some("127.0.0.1".to_owned()),
+            refresh_family_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_session() {
+        let mut store = HashmapSessionStore::default();
+        let expires_at = Utc::now().timestamp() + 600;
+        let session = session("session", "user@example.com", expires_at);
+
+        assert_eq!(store.add_session(session.clone()).await, Ok(()));
+
+        assert_eq!(store.get_session("session").await, Ok(session));
+
+        assert_eq!(
+            store.get_session("another").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_session_expired() {
+        let mut store = HashmapSessionStore::default();
+        let expires_at = Utc::now().timestamp() - 1;
+
+        store.add_session(session("session", "user@example.com", expires_at)).await.unwrap();
+
+        assert_eq!(
+            store.get_session("session").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("user@example.com").unwrap();
+        let expires_at = Utc::now().timestamp() + 600;
+
+        store.add_session(session("first", "user@example.com", expires_at)).await.unwrap();
+        store.add_session(session("second", "user@example.com", expires_at)).await.unwrap();
+        store.add_session(session("other", "another@example.com", expires_at)).await.unwrap();
+
+        let sessions = store.get_sessions(&email).await.unwrap();
+
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.iter().all(|session| session.email == "user@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_remove_session() {
+        let mut store = HashmapSessionStore::default();
+        let expires_at = Utc::now().timestamp() + 600;
+
+        store.add_session(session("session", "user@example.com", expires_at)).await.unwrap();
+
+        assert_eq!(store.remove_session("session").await, Ok(()));
+
+        assert_eq!(
+            store.get_session("session").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+
+        assert_eq!(
+            store.remove_session("session").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+}
@@ -4,10 +4,13 @@ use crate::domain::{
     data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
     email::Email,
 };
+use crate::utils::auth::{MAX_2FA_ATTEMPTS, MAX_2FA_RESENDS};
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
     codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    failed_attempts: HashMap<Email, u32>,
+    resends: HashMap<Email, u32>,
 }
 
 // TODO: implement TwoFACodeStore for HashmapTwoFACodeStore
@@ -16,12 +19,16 @@ pub struct HashmapTwoFACodeStore {
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(&mut self, email: Email, login_attempt_id: LoginAttemptId, code: TwoFACode)
         -> Result<(), TwoFACodeStoreError> {
+        self.failed_attempts.remove(&email);
+        self.resends.remove(&email);
         self.codes.insert(email, (login_attempt_id, code));
         Ok(())
     }
 
     async fn remove_code(&mut self, email: &Email)
         -> Result<(), TwoFACodeStoreError> {
+        self.failed_attempts.remove(email);
+        self.resends.remove(email);
         self.codes
             .remove(email)
             .map(|_| ())
@@ -30,10 +37,47 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
 
     async fn get_code(&self, email: &Email)
         -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        self.codes
+        let code = self.codes
             .get(email)
             .map(|user| user.clone())
-            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        match self.failed_attempts.get(email) {
+            Some(attempts) if *attempts >= MAX_2FA_ATTEMPTS => Err(TwoFACodeStoreError::TooManyAttempts),
+            _ => Ok(code),
+        }
+    }
+
+    async fn record_failed_attempt(&mut self, email: &Email)
+        -> Result<(), TwoFACodeStoreError> {
+        if !self.codes.contains_key(email) {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        let attempts = self.failed_attempts.entry(email.clone()).or_insert(0);
+        *attempts += 1;
+
+        match *attempts >= MAX_2FA_ATTEMPTS {
+            true => Err(TwoFACodeStoreError::TooManyAttempts),
+            false => Ok(()),
+        }
+    }
+
+    async fn resend_code(&mut self, email: &Email, code: TwoFACode)
+        -> Result<(), TwoFACodeStoreError> {
+        let (_, stored_code) = self.codes
+            .get_mut(email)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let resends = self.resends.entry(email.clone()).or_insert(0);
+
+        if *resends >= MAX_2FA_RESENDS {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+
+        *resends += 1;
+        *stored_code = code;
+        Ok(())
     }
 }
 
@@ -96,4 +140,86 @@ mod tests {
 
         assert_eq!(code_store, (login_attempt_id, fa_code));
     }
+
+    #[tokio::test]
+    async fn test_record_failed_attempt() {
+
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("user@example.com").unwrap();
+        let login_attempt_id = LoginAttemptId::parse(Uuid::new_v4().to_string()).unwrap();
+        let fa_code = TwoFACode::parse("123456".to_string()).unwrap();
+
+        assert_eq!(
+            store.record_failed_attempt(&email).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+
+        assert!(store
+            .add_code(email.clone(), login_attempt_id.clone(), fa_code.clone())
+            .await
+            .is_ok());
+
+        for _ in 1..MAX_2FA_ATTEMPTS {
+            assert_eq!(store.record_failed_attempt(&email).await, Ok(()));
+        }
+
+        assert_eq!(store.get_code(&email).await, Ok((login_attempt_id.clone(), fa_code.clone())));
+
+        assert_eq!(
+            store.record_failed_attempt(&email).await,
+            Err(TwoFACodeStoreError::TooManyAttempts)
+        );
+
+        assert_eq!(store.get_code(&email).await, Err(TwoFACodeStoreError::TooManyAttempts));
+
+        // Logging in again starts a new attempt with a fresh counter
+        assert!(store
+            .add_code(email.clone(), login_attempt_id.clone(), fa_code.clone())
+            .await
+            .is_ok());
+
+        assert_eq!(store.get_code(&email).await, Ok((login_attempt_id, fa_code)));
+    }
+
+    #[tokio::test]
+    async fn test_resend_code() {
+
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("user@example.com").unwrap();
+        let login_attempt_id = LoginAttemptId::parse(Uuid::new_v4().to_string()).unwrap();
+        let fa_code = TwoFACode::parse("123456".to_string()).unwrap();
+        let new_code = TwoFACode::parse("654321".to_string()).unwrap();
+
+        assert_eq!(
+            store.resend_code(&email, new_code.clone()).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+
+        assert!(store
+            .add_code(email.clone(), login_attempt_id.clone(), fa_code.clone())
+            .await
+            .is_ok());
+
+        for _ in 0..MAX_2FA_RESENDS {
+            assert_eq!(store.resend_code(&email, new_code.clone()).await, Ok(()));
+        }
+
+        // The attempt stays the same, only the code changes
+        assert_eq!(store.get_code(&email).await, Ok((login_attempt_id.clone(), new_code.clone())));
+
+        assert_eq!(
+            store.resend_code(&email, fa_code.clone()).await,
+            Err(TwoFACodeStoreError::TooManyResends)
+        );
+
+        assert_eq!(store.get_code(&email).await, Ok((login_attempt_id.clone(), new_code)));
+
+        // A new login attempt can be resent again
+        assert!(store
+            .add_code(email.clone(), login_attempt_id, fa_code.clone())
+            .await
+            .is_ok());
+
+        assert_eq!(store.resend_code(&email, fa_code).await, Ok(()));
+    }
 }
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{WebAuthnChallenge, WebAuthnChallengeStore, WebAuthnChallengeStoreError};
+use crate::utils::auth::WEBAUTHN_CHALLENGE_TTL_SECONDS;
+
+#[derive(Default)]
+pub struct HashmapWebAuthnChallengeStore {
+    // Ceremony the challenge was issued for and the time it expires at
+    challenges: HashMap<String, (WebAuthnChallenge, i64)>,
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for HashmapWebAuthnChallengeStore {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge) -> Result<(), WebAuthnChallengeStoreError> {
+        let expires_at = Utc::now().timestamp() + WEBAUTHN_CHALLENGE_TTL_SECONDS;
+        self.challenges.insert(challenge.challenge.clone(), (challenge, expires_at));
+        Ok(())
+    }
+
+    async fn consume_challenge(&mut self, challenge: &str)
+        -> Result<WebAuthnChallenge, WebAuthnChallengeStoreError> {
+        self.challenges
+            .remove(challenge)
+            .filter(|(_, expires_at)| *expires_at > Utc::now().timestamp())
+            .map(|(challenge, _)| challenge)
+            .ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::WebAuthnCeremony;
+
+    use super::*;
+
+    fn challenge() -> WebAuthnChallenge {
+        WebAuthnChallenge {
+            challenge: "challenge".to_owned(),
+            ceremony: WebAuthnCeremony::Registration,
+            email: Some("user@example.com".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consume_challenge() {
+        let mut store = HashmapWebAuthnChallengeStore::default();
+
+        assert_eq!(store.add_challenge(challenge()).await, Ok(()));
+
+        assert_eq!(store.consume_challenge("challenge").await, Ok(challenge()));
+
+        assert_eq!(
+            store.consume_challenge("challenge").await,
+            Err(WebAuthnChallengeStoreError::ChallengeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consume_expired_challenge() {
+        let mut store = HashmapWebAuthnChallengeStore::default();
+
+        store.challenges.insert("challenge".to_owned(), (challenge(), Utc::now().timestamp() - 1));
+
+        assert_eq!(
+            store.consume_challenge("challenge").await,
+            Err(WebAuthnChallengeStoreError::ChallengeNotFound)
+        );
+    }
+}
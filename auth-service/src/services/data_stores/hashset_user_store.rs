@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::domain::{AccountStatus, Email, Password, RecoveryCode, TwoFAMethod, User, UserPage, UserStore, UserStoreError};
 
 // #[derive(Debug, PartialEq)]
 // pub enum UserStoreError {
@@ -12,7 +12,8 @@ use crate::domain::{Email, Password, User, UserStore, UserStoreError};
 
 #[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>
+    users: HashMap<Email, User>,
+    recovery_codes: HashMap<Email, Vec<RecoveryCode>>,
 }
 
 #[async_trait::async_trait]
@@ -47,6 +48,151 @@ impl UserStore for HashmapUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn increment_token_version(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.token_version += 1;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.password = password;
+                user.password_reset_required = false;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                if user.status == AccountStatus::PendingVerification {
+                    user.status = AccountStatus::Active;
+                }
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_totp_secret(&mut self, email: &Email, secret: String) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.totp_secret = Some(secret);
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_two_fa_method(&mut self, email: &Email, method: TwoFAMethod) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.requires_2fa = true;
+                user.two_fa_method = method;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_recovery_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        self.recovery_codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn use_recovery_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), UserStoreError> {
+        let codes = self.recovery_codes.get_mut(email).ok_or(UserStoreError::InvalidCredentials)?;
+
+        match codes.iter().position(|stored| stored == code) {
+            Some(index) => {
+                codes.remove(index);
+                Ok(())
+            },
+            None => Err(UserStoreError::InvalidCredentials),
+        }
+    }
+
+    async fn set_roles(&mut self, email: &Email, roles: Vec<String>) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.roles = roles;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn list_users(&self, email_search: Option<&str>, offset: usize, limit: usize)
+        -> Result<UserPage, UserStoreError> {
+        let email_search = email_search.map(|search| search.to_lowercase());
+
+        let mut users: Vec<&User> = self.users
+            .values()
+            .filter(|user| match &email_search {
+                Some(search) => user.email.as_ref().to_lowercase().contains(search.as_str()),
+                None => true,
+            })
+            .collect();
+
+        users.sort_by(|a, b| a.email.as_ref().cmp(b.email.as_ref()));
+
+        Ok(UserPage {
+            total: users.len(),
+            users: users.into_iter().skip(offset).take(limit).cloned().collect(),
+        })
+    }
+
+    async fn set_status(&mut self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.status = status;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_password_reset_required(&mut self, email: &Email, required: bool) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.password_reset_required = required;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.requires_2fa = requires_2fa;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.remove(email) {
+            Some(_) => {
+                self.recovery_codes.remove(email);
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
 }
 
 // TODO: Add unit tests for your `HashmapUserStore` implementation
@@ -126,4 +272,265 @@ mod tests {
             Err(UserStoreError::InvalidCredentials)
         );
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_increment_token_version() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+
+        assert_eq!(user_store.increment_token_version(&user.email).await, Ok(()));
+
+        assert_eq!(user_store.get_user(&user.email).await.unwrap().token_version, 1);
+
+        assert_eq!(
+            user_store.increment_token_version(&Email::parse("another@example.com").unwrap()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_password() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+
+        let new_password = Password::parse("new password").unwrap();
+
+        assert_eq!(user_store.update_password(&user.email, new_password.clone()).await, Ok(()));
+
+        assert_eq!(user_store.validate_user(&user.email, &new_password).await, Ok(()));
+
+        assert_eq!(
+            user_store.validate_user(&user.email, &user.password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+
+        assert_eq!(
+            user_store.update_password(&Email::parse("another@example.com").unwrap(), new_password).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+
+        assert_eq!(user_store.get_user(&user.email).await.unwrap().status, AccountStatus::PendingVerification);
+
+        assert_eq!(user_store.mark_email_verified(&user.email).await, Ok(()));
+
+        assert_eq!(user_store.get_user(&user.email).await.unwrap().status, AccountStatus::Active);
+
+        // Verifying again doesn't lift a suspension
+        assert_eq!(user_store.set_status(&user.email, AccountStatus::Suspended).await, Ok(()));
+        assert_eq!(user_store.mark_email_verified(&user.email).await, Ok(()));
+        assert_eq!(user_store.get_user(&user.email).await.unwrap().status, AccountStatus::Suspended);
+
+        assert_eq!(
+            user_store.mark_email_verified(&Email::parse("another@example.com").unwrap()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_two_fa_method() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+
+        assert_eq!(user_store.set_totp_secret(&user.email, "JBSWY3DPEHPK3PXP".to_owned()).await, Ok(()));
+
+        let stored = user_store.get_user(&user.email).await.unwrap();
+        assert_eq!(stored.totp_secret, Some("JBSWY3DPEHPK3PXP".to_owned()));
+        assert!(!stored.requires_2fa);
+        assert_eq!(stored.two_fa_method, TwoFAMethod::Email);
+
+        assert_eq!(user_store.set_two_fa_method(&user.email, TwoFAMethod::Totp).await, Ok(()));
+
+        let stored = user_store.get_user(&user.email).await.unwrap();
+        assert!(stored.requires_2fa);
+        assert_eq!(stored.two_fa_method, TwoFAMethod::Totp);
+
+        assert_eq!(
+            user_store.set_two_fa_method(&Email::parse("another@example.com").unwrap(), TwoFAMethod::Totp).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_use_recovery_code() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            true
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+
+        let code = RecoveryCode::default();
+
+        assert_eq!(
+            user_store.use_recovery_code(&user.email, &code).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+
+        assert_eq!(user_store.set_recovery_codes(&user.email, vec![code.clone()]).await, Ok(()));
+
+        assert_eq!(user_store.use_recovery_code(&user.email, &code).await, Ok(()));
+
+        assert_eq!(
+            user_store.use_recovery_code(&user.email, &code).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+
+        // A new set replaces the codes handed out before
+        assert_eq!(user_store.set_recovery_codes(&user.email, vec![code.clone()]).await, Ok(()));
+        assert_eq!(user_store.set_recovery_codes(&user.email, vec![RecoveryCode::default()]).await, Ok(()));
+
+        assert_eq!(
+            user_store.use_recovery_code(&user.email, &code).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+
+        assert_eq!(
+            user_store.set_recovery_codes(&Email::parse("another@example.com").unwrap(), vec![code]).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_roles() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+        assert!(user_store.get_user(&user.email).await.unwrap().roles.is_empty());
+
+        assert_eq!(user_store.set_roles(&user.email, vec!["admin".to_owned()]).await, Ok(()));
+        assert_eq!(user_store.get_user(&user.email).await.unwrap().roles, vec!["admin".to_owned()]);
+
+        assert_eq!(
+            user_store.set_roles(&Email::parse("another@example.com").unwrap(), vec![]).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        for email in ["carol@example.com", "alice@example.com", "bob@other.com"] {
+            let user = User::new(Email::parse(email).unwrap(), Password::parse("password").unwrap(), false);
+            assert_eq!(user_store.add_user(user).await, Ok(()));
+        }
+
+        let page = user_store.list_users(None, 0, 2).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(
+            page.users.iter().map(|user| user.email.as_ref()).collect::<Vec<_>>(),
+            vec!["alice@example.com", "bob@other.com"]
+        );
+
+        let page = user_store.list_users(None, 2, 2).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.users.len(), 1);
+        assert_eq!(page.users[0].email.as_ref(), "carol@example.com");
+
+        let page = user_store.list_users(Some("EXAMPLE"), 0, 10).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(
+            page.users.iter().map(|user| user.email.as_ref()).collect::<Vec<_>>(),
+            vec!["alice@example.com", "carol@example.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_flags() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+
+        assert_eq!(user_store.set_status(&user.email, AccountStatus::Suspended).await, Ok(()));
+        assert_eq!(user_store.set_password_reset_required(&user.email, true).await, Ok(()));
+        assert_eq!(user_store.set_requires_2fa(&user.email, true).await, Ok(()));
+
+        let stored_user = user_store.get_user(&user.email).await.unwrap();
+        assert_eq!(stored_user.status, AccountStatus::Suspended);
+        assert!(stored_user.password_reset_required);
+        assert!(stored_user.requires_2fa);
+
+        // Setting a new password clears the forced reset
+        assert_eq!(user_store.update_password(&user.email, Password::parse("new_password").unwrap()).await, Ok(()));
+        assert!(!user_store.get_user(&user.email).await.unwrap().password_reset_required);
+
+        let unknown = Email::parse("another@example.com").unwrap();
+        assert_eq!(user_store.set_status(&unknown, AccountStatus::Suspended).await, Err(UserStoreError::UserNotFound));
+        assert_eq!(user_store.set_password_reset_required(&unknown, true).await, Err(UserStoreError::UserNotFound));
+        assert_eq!(user_store.set_requires_2fa(&unknown, true).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+
+        let mut user_store = HashmapUserStore::default();
+
+        let user = User::new(
+            Email::parse("user@example.com").unwrap(),
+            Password::parse("password").unwrap(),
+            false
+        );
+
+        assert_eq!(user_store.add_user(user.clone()).await, Ok(()));
+        assert_eq!(user_store.delete_user(&user.email).await, Ok(()));
+        assert_eq!(user_store.get_user(&user.email).await, Err(UserStoreError::UserNotFound));
+        assert_eq!(user_store.delete_user(&user.email).await, Err(UserStoreError::UserNotFound));
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+
+use crate::domain::{Email, EmailClient, EmailClientError, EmailMessage};
+
+#[derive(Clone)]
+pub struct HttpEmailConfig {
+    // e.g. `https://api.postmarkapp.com`, overridden in tests to point at a local server
+    pub base_url: String,
+    pub token: String,
+    // Mailbox emails are sent from, e.g. `Auth Service <no-reply@example.com>`
+    pub from: String,
+    // Limits every single request made to the provider
+    pub timeout: Duration,
+    // How often a request is repeated after a server error before giving up
+    pub max_retries: u32,
+    // Delay before the first retry, doubled on each further one
+    pub retry_backoff: Duration,
+}
+
+// Sends emails through a transactional email HTTP API speaking Postmark's wire format
+pub struct HttpEmailClient {
+    http_client: Client,
+    base_url: String,
+    token: String,
+    sender: String,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+}
+
+impl HttpEmailClient {
+    pub fn new(config: &HttpEmailConfig) -> Result<Self, EmailClientError> {
+        let http_client = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| EmailClientError::Unavailable(e.to_string()))?;
+
+        Ok(HttpEmailClient {
+            http_client,
+            base_url: config.base_url.trim_end_matches('/').to_owned(),
+            token: config.token.clone(),
+            sender: config.from.clone(),
+            max_retries: config.max_retries,
+            retry_backoff: config.retry_backoff,
+        })
+    }
+
+    async fn try_send(&self, request: &SendEmailRequest<'_>) -> Result<(), EmailClientError> {
+        let response = self.http_client
+            .post(format!("{}/email", self.base_url))
+            .header("X-Postmark-Server-Token", &self.token)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| EmailClientError::Unavailable(e.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let error = format!("{}: {}", status, body);
+
+        // Throttling and server errors are temporary, any other client error means the
+        // request itself is wrong, e.g. an invalid token or an inactive recipient
+        match status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            true => Err(EmailClientError::Unavailable(error)),
+            false => Err(EmailClientError::Rejected(error)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for HttpEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError> {
+        let request = SendEmailRequest {
+            from: &self.sender,
+            to: recipient.as_ref(),
+            subject: &message.subject,
+            html_body: &message.html,
+            text_body: &message.text,
+        };
+
+        let mut attempt = 0;
+        loop {
+            match self.try_send(&request).await {
+                Err(error) if error.is_retryable() && attempt < self.max_retries => {
+                    tokio::time::sleep(self.retry_backoff.saturating_mul(2u32.saturating_pow(attempt))).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
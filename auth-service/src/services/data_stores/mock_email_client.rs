@@ -1,4 +1,4 @@
-use crate::domain::{Email, EmailClient};
+use crate::domain::{Email, EmailClient, EmailClientError, EmailMessage};
 
 pub struct MockEmailClient;
 
@@ -7,15 +7,14 @@ impl EmailClient for MockEmailClient {
     async fn send_email(
         &self,
         recipient: &Email,
-        subject: &str,
-        content: &str,
-    ) -> Result<(), String> {
-        // Our mock email client will simply log the recipient, subject, and content to standard output
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError> {
+        // Our mock email client will simply log the recipient, subject, and plain text content to standard output
         println!(
             "Sending email to {} with subject: {} and content: {}",
             recipient.as_ref(),
-            subject,
-            content
+            message.subject,
+            message.text
         );
 
         Ok(())
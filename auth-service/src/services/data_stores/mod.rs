@@ -1,15 +1,59 @@
 mod hashset_user_store;
 mod hashmap_banned_token_store;
 mod hashmap_two_fa_code_store;
+mod hashmap_refresh_token_store;
+mod hashmap_session_store;
+mod hashmap_password_reset_token_store;
+mod hashmap_email_verification_token_store;
+mod hashmap_credential_store;
+mod hashmap_webauthn_challenge_store;
+mod hashmap_login_attempt_store;
+mod hashmap_rate_limit_store;
+mod hashmap_email_outbox_store;
+mod hashmap_audit_log_store;
 pub mod mock_email_client;
+mod smtp_email_client;
+mod http_email_client;
 mod postgres_user_store;
+mod postgres_credential_store;
+mod postgres_email_outbox_store;
+mod postgres_audit_log_store;
 mod redis_banned_token_store;
 mod redis_two_fa_code_store;
+mod redis_refresh_token_store;
+mod redis_session_store;
+mod redis_password_reset_token_store;
+mod redis_email_verification_token_store;
+mod redis_webauthn_challenge_store;
+mod redis_login_attempt_store;
+mod redis_rate_limit_store;
 
 pub use hashset_user_store::*;
 pub use hashmap_banned_token_store::*;
 pub use hashmap_two_fa_code_store::*;
+pub use hashmap_refresh_token_store::*;
+pub use hashmap_session_store::*;
+pub use hashmap_password_reset_token_store::*;
+pub use hashmap_email_verification_token_store::*;
+pub use hashmap_credential_store::*;
+pub use hashmap_webauthn_challenge_store::*;
+pub use hashmap_login_attempt_store::*;
+pub use hashmap_rate_limit_store::*;
+pub use hashmap_email_outbox_store::*;
+pub use hashmap_audit_log_store::*;
 pub use mock_email_client::*;
+pub use smtp_email_client::*;
+pub use http_email_client::*;
 pub use postgres_user_store::*;
+pub use postgres_credential_store::*;
+pub use postgres_email_outbox_store::*;
+pub use postgres_audit_log_store::*;
 pub use redis_banned_token_store::*;
 pub use redis_two_fa_code_store::*;
+pub use redis_refresh_token_store::*;
+pub use redis_session_store::*;
+pub use redis_password_reset_token_store::*;
+pub use redis_email_verification_token_store::*;
+pub use redis_webauthn_challenge_store::*;
+pub use redis_login_attempt_store::*;
+pub use redis_rate_limit_store::*;
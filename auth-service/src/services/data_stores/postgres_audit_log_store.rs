@@ -0,0 +1,70 @@
+use sqlx::{PgPool, Row};
+use sqlx::postgres::PgRow;
+
+use crate::domain::{AuditEvent, AuditEventType, AuditLogStore, AuditLogStoreError, Email};
+
+pub struct PostgresAuditLogStore {
+    pool: PgPool,
+}
+
+impl PostgresAuditLogStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for PostgresAuditLogStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_events (id, email, event_type, ip, user_agent, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+            .bind(&event.id)
+            .bind(&event.email)
+            .bind(event.event_type.as_str())
+            .bind(&event.ip)
+            .bind(&event.user_agent)
+            .bind(event.created_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| AuditLogStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_events(&self, email: &Email) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        sqlx::query("SELECT * FROM audit_events WHERE email = $1 ORDER BY created_at")
+            .bind(email.as_ref())
+            .try_map(audit_event_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| AuditLogStoreError::UnexpectedError)
+    }
+
+    async fn delete_events(&mut self, email: &Email) -> Result<(), AuditLogStoreError> {
+        sqlx::query("DELETE FROM audit_events WHERE email = $1")
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| AuditLogStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+fn audit_event_from_row(row: PgRow) -> Result<AuditEvent, sqlx::Error> {
+    let event_type = AuditEventType::parse(row.get("event_type"))
+        .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+    Ok(AuditEvent {
+        id: row.get("id"),
+        email: row.get("email"),
+        event_type,
+        ip: row.get("ip"),
+        user_agent: row.get("user_agent"),
+        created_at: row.get("created_at"),
+    })
+}
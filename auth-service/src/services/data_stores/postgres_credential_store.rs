@@ -0,0 +1,82 @@
+use sqlx::{PgPool, Row};
+use sqlx::postgres::PgRow;
+
+use crate::domain::{CredentialStore, CredentialStoreError, Email, WebAuthnCredential};
+
+pub struct PostgresCredentialStore {
+    pool: PgPool,
+}
+
+impl PostgresCredentialStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialStore for PostgresCredentialStore {
+    async fn add_credential(&mut self, credential: WebAuthnCredential) -> Result<(), CredentialStoreError> {
+        sqlx::query(
+            r#"
+            INSERT INTO webauthn_credentials (id, email, public_key, sign_count, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+            .bind(&credential.id)
+            .bind(&credential.email)
+            .bind(&credential.public_key)
+            .bind(credential.sign_count as i64)
+            .bind(credential.created_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e.as_database_error().map(|e| e.is_unique_violation()) {
+                Some(true) => CredentialStoreError::CredentialAlreadyExists,
+                _ => CredentialStoreError::UnexpectedError,
+            })?;
+
+        Ok(())
+    }
+
+    async fn get_credential(&self, id: &str) -> Result<WebAuthnCredential, CredentialStoreError> {
+        sqlx::query("SELECT * FROM webauthn_credentials WHERE id = $1")
+            .bind(id)
+            .map(credential_from_row)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| CredentialStoreError::UnexpectedError)?
+            .ok_or(CredentialStoreError::CredentialNotFound)
+    }
+
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, CredentialStoreError> {
+        sqlx::query("SELECT * FROM webauthn_credentials WHERE email = $1 ORDER BY created_at")
+            .bind(email.as_ref())
+            .map(credential_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| CredentialStoreError::UnexpectedError)
+    }
+
+    async fn update_sign_count(&mut self, id: &str, sign_count: u32) -> Result<(), CredentialStoreError> {
+        let result = sqlx::query("UPDATE webauthn_credentials SET sign_count = $1 WHERE id = $2")
+            .bind(sign_count as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| CredentialStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(CredentialStoreError::CredentialNotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn credential_from_row(row: PgRow) -> WebAuthnCredential {
+    WebAuthnCredential {
+        id: row.get("id"),
+        email: row.get("email"),
+        public_key: row.get("public_key"),
+        sign_count: row.get::<i64, _>("sign_count") as u32,
+        created_at: row.get("created_at"),
+    }
+}
@@ -0,0 +1,141 @@
+use sqlx::{PgPool, Row};
+use sqlx::postgres::PgRow;
+
+use crate::domain::{Email, EmailMessage, EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxEmailStatus};
+
+pub struct PostgresEmailOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for PostgresEmailOutboxStore {
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_outbox (id, recipient, subject, html_body, text_body, status, attempts, next_attempt_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        )
+            .bind(&email.id)
+            .bind(&email.recipient)
+            .bind(&email.message.subject)
+            .bind(&email.message.html)
+            .bind(&email.message.text)
+            .bind(email.status.as_str())
+            .bind(email.attempts as i32)
+            .bind(email.next_attempt_at)
+            .bind(email.created_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn claim_due(&mut self, now: i64, lease_until: i64, limit: usize)
+        -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        // SKIP LOCKED lets several workers claim at the same time without handing out the same email twice
+        sqlx::query(
+            r#"
+            UPDATE email_outbox SET attempts = attempts + 1, next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY next_attempt_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+            .bind(now)
+            .bind(lease_until)
+            .bind(limit as i64)
+            .try_map(outbox_email_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| EmailOutboxStoreError::UnexpectedError)
+    }
+
+    async fn mark_sent(&mut self, id: &str) -> Result<(), EmailOutboxStoreError> {
+        let result = sqlx::query("UPDATE email_outbox SET status = 'sent', last_error = NULL WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| EmailOutboxStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(EmailOutboxStoreError::EmailNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn mark_failed(&mut self, id: &str, error: &str, retry_at: Option<i64>) -> Result<(), EmailOutboxStoreError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET status = CASE WHEN $3::BIGINT IS NULL THEN 'dead' ELSE 'pending' END,
+                next_attempt_at = COALESCE($3, next_attempt_at),
+                last_error = $2
+            WHERE id = $1
+            "#,
+        )
+            .bind(id)
+            .bind(error)
+            .bind(retry_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| EmailOutboxStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(EmailOutboxStoreError::EmailNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_emails(&self, recipient: &Email) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        sqlx::query("SELECT * FROM email_outbox WHERE recipient = $1 ORDER BY created_at")
+            .bind(recipient.as_ref())
+            .try_map(outbox_email_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| EmailOutboxStoreError::UnexpectedError)
+    }
+
+    async fn delete_emails(&mut self, recipient: &Email) -> Result<(), EmailOutboxStoreError> {
+        sqlx::query("DELETE FROM email_outbox WHERE recipient = $1")
+            .bind(recipient.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+fn outbox_email_from_row(row: PgRow) -> Result<OutboxEmail, sqlx::Error> {
+    let status = OutboxEmailStatus::parse(row.get("status"))
+        .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+    Ok(OutboxEmail {
+        id: row.get("id"),
+        recipient: row.get("recipient"),
+        message: EmailMessage {
+            subject: row.get("subject"),
+            html: row.get("html_body"),
+            text: row.get("text_body"),
+        },
+        status,
+        attempts: row.get::<i32, _>("attempts") as u32,
+        next_attempt_at: row.get("next_attempt_at"),
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+    })
+}
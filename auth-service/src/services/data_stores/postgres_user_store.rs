@@ -10,7 +10,7 @@ use sqlx::postgres::PgRow;
 // use sqlx::postgres::PgRow;
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+    AccountStatus, Email, Password, RecoveryCode, TwoFAMethod, User, UserPage,
 };
 
 pub struct PostgresUserStore {
@@ -58,11 +58,7 @@ impl UserStore for PostgresUserStore {
 
         sqlx::query("SELECT * FROM users WHERE email = $1")
             .bind(email.as_ref())
-            .map(|row: PgRow| Ok(User {
-                email: Email::parse(row.get("email")).unwrap(),
-                password: Password::parse(row.get("password_hash")).unwrap(),
-                requires_2fa: row.get("requires_2fa"),
-            }))
+            .map(|row: PgRow| Ok(user_from_row(row)))
             .fetch_optional(&self.pool)
             .await
             .unwrap().ok_or(UserStoreError::UserNotFound)?
@@ -77,6 +73,254 @@ impl UserStore for PostgresUserStore {
         )
         .map_err(|_| UserStoreError::InvalidCredentials)
     }
+
+    async fn increment_token_version(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET token_version = token_version + 1 WHERE email = $1")
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref())
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query("UPDATE users SET password_hash = $1, password_reset_required = FALSE WHERE email = $2")
+            .bind(&password_hash)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET status = CASE WHEN status = 'pending-verification' THEN 'active' ELSE status END
+            WHERE email = $1
+            "#,
+        )
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_totp_secret(&mut self, email: &Email, secret: String) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET totp_secret = $1 WHERE email = $2")
+            .bind(&secret)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_two_fa_method(&mut self, email: &Email, method: TwoFAMethod) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET requires_2fa = TRUE, two_fa_method = $1 WHERE email = $2")
+            .bind(method.as_ref())
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_recovery_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), UserStoreError> {
+        // Recovery codes log the user in just like a password, so they are hashed the same way
+        let code_hashes = codes
+            .iter()
+            .map(|code| compute_password_hash(code.as_ref()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let mut transaction = self.pool.begin().await.map_err(|_| UserStoreError::UnexpectedError)?;
+
+        sqlx::query("DELETE FROM recovery_codes WHERE email = $1")
+            .bind(email.as_ref())
+            .execute(&mut *transaction)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        for code_hash in code_hashes {
+            sqlx::query("INSERT INTO recovery_codes (email, code_hash) VALUES ($1, $2)")
+                .bind(email.as_ref())
+                .bind(&code_hash)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| match e.as_database_error().map(|e| e.is_foreign_key_violation()) {
+                    Some(true) => UserStoreError::UserNotFound,
+                    _ => UserStoreError::UnexpectedError,
+                })?;
+        }
+
+        transaction.commit().await.map_err(|_| UserStoreError::UnexpectedError)
+    }
+
+    async fn use_recovery_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), UserStoreError> {
+        let rows = sqlx::query("SELECT id, code_hash FROM recovery_codes WHERE email = $1")
+            .bind(email.as_ref())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let id: i64 = rows
+            .iter()
+            .find(|row| verify_password_hash(row.get("code_hash"), code.as_ref()).is_ok())
+            .map(|row| row.get("id"))
+            .ok_or(UserStoreError::InvalidCredentials)?;
+
+        let result = sqlx::query("DELETE FROM recovery_codes WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        // The code was used by a concurrent request in the meantime
+        match result.rows_affected() {
+            0 => Err(UserStoreError::InvalidCredentials),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_roles(&mut self, email: &Email, roles: Vec<String>) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET roles = $1 WHERE email = $2")
+            .bind(&roles)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list_users(&self, email_search: Option<&str>, offset: usize, limit: usize)
+        -> Result<UserPage, UserStoreError> {
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM users WHERE $1::TEXT IS NULL OR strpos(lower(email), lower($1)) > 0",
+        )
+            .bind(email_search)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let users = sqlx::query(
+            r#"
+            SELECT * FROM users
+            WHERE $1::TEXT IS NULL OR strpos(lower(email), lower($1)) > 0
+            ORDER BY email
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+            .bind(email_search)
+            .bind(limit as i64)
+            .bind(offset as i64)
+            .map(user_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        Ok(UserPage { users, total: total as usize })
+    }
+
+    async fn set_status(&mut self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET status = $1 WHERE email = $2")
+            .bind(status.as_ref())
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_password_reset_required(&mut self, email: &Email, required: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET password_reset_required = $1 WHERE email = $2")
+            .bind(required)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET requires_2fa = $1 WHERE email = $2")
+            .bind(requires_2fa)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        // Recovery codes and WebAuthn credentials are removed by their foreign keys
+        let result = sqlx::query("DELETE FROM users WHERE email = $1")
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn user_from_row(row: PgRow) -> User {
+    User {
+        email: Email::parse(row.get("email")).unwrap(),
+        password: Password::parse(row.get("password_hash")).unwrap(),
+        requires_2fa: row.get("requires_2fa"),
+        token_version: row.get("token_version"),
+        two_fa_method: TwoFAMethod::parse(row.get("two_fa_method")).unwrap_or_default(),
+        totp_secret: row.get("totp_secret"),
+        roles: row.get("roles"),
+        // A status this version doesn't know about must not let the user in
+        status: AccountStatus::parse(row.get("status")).unwrap_or(AccountStatus::Suspended),
+        password_reset_required: row.get("password_reset_required"),
+    }
 }
 
 // Helper function to verify if a given password matches an expected hash
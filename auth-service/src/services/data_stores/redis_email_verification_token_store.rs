@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError},
+        Email,
+    },
+    utils::auth::{EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS},
+};
+
+pub struct RedisEmailVerificationTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailVerificationTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for RedisEmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        token: EmailVerificationToken,
+        email: Email,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        // The cooldown key only exists while the last token is too recent to send another one
+        let started: Option<String> = redis::cmd("SET")
+            .arg(get_cooldown_key(&email))
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS as u64)
+            .query(&mut *conn)
+            .map_err(|_| EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        if started.is_none() {
+            return Err(EmailVerificationTokenStoreError::TooManyRequests);
+        }
+
+        let _: () = conn
+            .set_ex(get_token_key(&token), email.as_ref(), EMAIL_VERIFICATION_TOKEN_TTL_SECONDS as u64)
+            .map_err(|_| EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        let value: Option<String> = self.conn.write().await
+            .get_del(get_token_key(token))
+            .map_err(|_| EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(EmailVerificationTokenStoreError::TokenNotFound)?;
+
+        Email::parse(&value).map_err(|_| EmailVerificationTokenStoreError::UnexpectedError)
+    }
+}
+
+const EMAIL_VERIFICATION_TOKEN_KEY_PREFIX: &str = "email_verification_token:";
+const EMAIL_VERIFICATION_COOLDOWN_KEY_PREFIX: &str = "email_verification_cooldown:";
+
+fn get_token_key(token: &EmailVerificationToken) -> String {
+    format!("{}{}", EMAIL_VERIFICATION_TOKEN_KEY_PREFIX, token.as_ref())
+}
+
+fn get_cooldown_key(email: &Email) -> String {
+    format!("{}{}", EMAIL_VERIFICATION_COOLDOWN_KEY_PREFIX, email.as_ref())
+}
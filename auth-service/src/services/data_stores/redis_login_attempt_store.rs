@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{LoginAttemptKey, LoginAttemptStore, LoginAttemptStoreError},
+    utils::auth::{login_lockout_seconds, LOGIN_FAILURE_TTL_SECONDS},
+};
+
+pub struct RedisLoginAttemptStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisLoginAttemptStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for RedisLoginAttemptStore {
+    async fn get_lockout(&self, key: &LoginAttemptKey) -> Result<Option<i64>, LoginAttemptStoreError> {
+        // The lockout key expires when the lockout ends, so its TTL is the time left
+        let ttl: i64 = self.conn.write().await.ttl(get_lockout_key(key))
+            .map_err(|_| LoginAttemptStoreError::UnexpectedError)?;
+
+        Ok(Some(ttl).filter(|ttl| *ttl > 0))
+    }
+
+    async fn record_failure(&mut self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let (failures,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(get_failures_key(key), 1)
+            .expire(get_failures_key(key), LOGIN_FAILURE_TTL_SECONDS)
+            .ignore()
+            .query(&mut *conn)
+            .map_err(|_| LoginAttemptStoreError::UnexpectedError)?;
+
+        let lockout = login_lockout_seconds(key, failures);
+
+        if lockout > 0 {
+            let _: () = conn.set_ex(get_lockout_key(key), failures, lockout as u64)
+                .map_err(|_| LoginAttemptStoreError::UnexpectedError)?;
+        }
+
+        Ok(())
+    }
+
+    async fn reset(&mut self, key: &LoginAttemptKey) -> Result<(), LoginAttemptStoreError> {
+        let _: () = self.conn.write().await.del(&[get_failures_key(key), get_lockout_key(key)])
+            .map_err(|_| LoginAttemptStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+const LOGIN_FAILURES_PREFIX: &str = "login_failures:";
+const LOGIN_LOCKOUT_PREFIX: &str = "login_lockout:";
+
+fn get_failures_key(key: &LoginAttemptKey) -> String {
+    format!("{}{}", LOGIN_FAILURES_PREFIX, key_suffix(key))
+}
+
+fn get_lockout_key(key: &LoginAttemptKey) -> String {
+    format!("{}{}", LOGIN_LOCKOUT_PREFIX, key_suffix(key))
+}
+
+fn key_suffix(key: &LoginAttemptKey) -> String {
+    match key {
+        LoginAttemptKey::Email(email) => format!("email:{}", email.as_ref()),
+        LoginAttemptKey::Ip(ip) => format!("ip:{}", ip),
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+        Email,
+    },
+    utils::auth::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisPasswordResetTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        token: PasswordResetToken,
+        email: Email,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let _: () = self.conn.write().await
+            .set_ex(get_key(&token), email.as_ref(), PASSWORD_RESET_TOKEN_TTL_SECONDS as u64)
+            .map_err(|_| PasswordResetTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        // GETDEL reads and removes the token in one step, so it can't be used twice concurrently
+        let value: Option<String> = self.conn.write().await
+            .get_del(get_key(token))
+            .map_err(|_| PasswordResetTokenStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+
+        Email::parse(&value).map_err(|_| PasswordResetTokenStoreError::UnexpectedError)
+    }
+}
+
+const PASSWORD_RESET_TOKEN_KEY_PREFIX: &str = "password_reset_token:";
+
+fn get_key(token: &PasswordResetToken) -> String {
+    format!("{}{}", PASSWORD_RESET_TOKEN_KEY_PREFIX, token.as_ref())
+}
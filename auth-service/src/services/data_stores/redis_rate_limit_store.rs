@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use redis::{Connection, Script};
+use tokio::sync::RwLock;
+
+use crate::domain::{RateLimit, RateLimitStatus, RateLimitStore, RateLimitStoreError};
+
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<Connection>>,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn, script: Script::new(TAKE_TOKEN_SCRIPT) }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn take_token(&mut self, key: &str, limit: &RateLimit) -> Result<RateLimitStatus, RateLimitStoreError> {
+        let (allowed, tokens): (bool, String) = self.script
+            .key(get_key(key))
+            .arg(limit.capacity)
+            .arg(limit.period_seconds as u64 * 1000)
+            .arg(Utc::now().timestamp_millis())
+            .invoke(&mut *self.conn.write().await)
+            .map_err(|_| RateLimitStoreError::UnexpectedError)?;
+
+        let tokens: f64 = tokens.parse().map_err(|_| RateLimitStoreError::UnexpectedError)?;
+
+        Ok(RateLimitStatus::new(limit, tokens, allowed))
+    }
+}
+
+// Same refill as `TokenBucket::take`, run inside Redis so instances sharing a bucket can't race each other.
+// Tokens are returned as a string, since Redis would truncate a Lua number to an integer.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local period_ms = tonumber(ARGV[2])
+local now_ms = tonumber(ARGV[3])
+local rate = capacity / period_ms
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at_ms')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at_ms = tonumber(bucket[2]) or now_ms
+
+tokens = math.min(capacity, tokens + math.max(0, now_ms - updated_at_ms) * rate)
+
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at_ms', now_ms)
+redis.call('PEXPIRE', KEYS[1], math.ceil((capacity - tokens) / rate))
+
+return {allowed, tostring(tokens)}
+"#;
+
+const RATE_LIMIT_PREFIX: &str = "rate_limit:";
+
+fn get_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_PREFIX, key)
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::data_stores::{RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let serialized_data = serde_json::to_string(&record)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let _: () = self.conn.write().await
+            .set_ex(get_token_key(&token), serialized_data, REFRESH_TOKEN_TTL_SECONDS as u64)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let value: String = conn
+            .get(get_token_key(token))
+            .map_err(|_| RefreshTokenStoreError::TokenNotFound)?;
+
+        let mut record: RefreshTokenRecord = serde_json::from_str(&value)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let is_revoked: bool = conn
+            .exists(get_family_key(&record.family_id))
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        if is_revoked {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        if record.used {
+            return Err(RefreshTokenStoreError::TokenReused(record));
+        }
+
+        // Keep the used token around until it expires so a replay can still be detected
+        record.used = true;
+
+        let serialized_data = serde_json::to_string(&record)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .set_ex(get_token_key(token), serialized_data, REFRESH_TOKEN_TTL_SECONDS as u64)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(record)
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let _: () = self.conn.write().await
+            .set_ex(get_family_key(family_id), true, REFRESH_TOKEN_TTL_SECONDS as u64)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const REVOKED_FAMILY_KEY_PREFIX: &str = "revoked_refresh_family:";
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token.as_ref())
+}
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REVOKED_FAMILY_KEY_PREFIX, family_id)
+}
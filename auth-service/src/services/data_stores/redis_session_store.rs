@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{Session, SessionStore, SessionStoreError},
+        Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let serialized_data = serde_json::to_string(&session)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        // The session is kept until it ends, see `Session::expires_at`
+        let ttl: u64 = (session.expires_at - Utc::now().timestamp())
+            .max(1)
+            .try_into()
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .set_ex(get_session_key(&session.id), serialized_data, ttl)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        let user_sessions_key = get_user_sessions_key(&session.email);
+
+        let _: () = conn
+            .sadd(&user_sessions_key, &session.id)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        // Long enough for the longest session, expired ids are pruned by `get_sessions`
+        let _: () = conn
+            .expire(&user_sessions_key, REFRESH_TOKEN_TTL_SECONDS)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let value: Option<String> = self.conn.write().await
+            .get(get_session_key(id))
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        match value {
+            Some(value) => serde_json::from_str(&value)
+                .map_err(|_| SessionStoreError::UnexpectedError),
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let user_sessions_key = get_user_sessions_key(email.as_ref());
+
+        let mut conn = self.conn.write().await;
+
+        let ids: Vec<String> = conn
+            .smembers(&user_sessions_key)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let value: Option<String> = conn
+                .get(get_session_key(&id))
+                .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+            match value {
+                Some(value) => sessions.push(
+                    serde_json::from_str::<Session>(&value)
+                        .map_err(|_| SessionStoreError::UnexpectedError)?,
+                ),
+                // The session has expired, so drop it from the user's index as well
+                None => {
+                    let _: () = conn
+                        .srem(&user_sessions_key, &id)
+                        .map_err(|_| SessionStoreError::UnexpectedError)?;
+                }
+            }
+        }
+
+        sessions.sort_by_key(|session| session.created_at);
+
+        Ok(sessions)
+    }
+
+    async fn remove_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let session = self.get_session(id).await?;
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .del(get_session_key(id))
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .srem(get_user_sessions_key(&session.email), id)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+const SESSION_KEY_PREFIX: &str = "session:";
+const USER_SESSIONS_KEY_PREFIX: &str = "user_sessions:";
+
+fn get_session_key(id: &str) -> String {
+    format!("{}{}", SESSION_KEY_PREFIX, id)
+}
+
+fn get_user_sessions_key(email: &str) -> String {
+    format!("{}{}", USER_SESSIONS_KEY_PREFIX, email)
+}
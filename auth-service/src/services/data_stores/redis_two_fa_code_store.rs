@@ -8,6 +8,7 @@ use crate::domain::{
     data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
     Email,
 };
+use crate::utils::auth::{MAX_2FA_ATTEMPTS, MAX_2FA_RESENDS};
 
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
@@ -33,8 +34,12 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             code.as_ref().to_owned(),
         )).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
 
-        let _: () = self.conn.write().await
+        // A new code starts a new login attempt, so the failures of the previous one no longer count
+        let _: () = redis::pipe()
+            .atomic()
             .set_ex(get_key(&email), serialized_data, TEN_MINUTES_IN_SECONDS)
+            .del(&[get_attempts_key(&email), get_resends_key(&email)])
+            .query(&mut *self.conn.write().await)
             .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
 
         Ok(())
@@ -46,7 +51,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         // 2. Call the del command on the Redis connection to delete the 2FA code entry.
         // Return TwoFACodeStoreError::UnexpectedError if the operation fails.
 
-        let _: () = self.conn.write().await.del(get_key(email))
+        let _: () = self.conn.write().await.del(&[get_key(email), get_attempts_key(email), get_resends_key(email)])
             .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
 
         Ok(())
@@ -64,8 +69,17 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         // Then, parse the login attempt ID string and 2FA code string into a LoginAttemptId and TwoFACode type respectively.
         // Return TwoFACodeStoreError::UnexpectedError if parsing fails.
 
-        match self.conn.write().await.get::<_, String>(get_key(email)) {
+        let mut conn = self.conn.write().await;
+
+        match conn.get::<_, String>(get_key(email)) {
             Ok(value) => {
+                let attempts: Option<u32> = conn.get(get_attempts_key(email))
+                    .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+                if attempts.is_some_and(|attempts| attempts >= MAX_2FA_ATTEMPTS) {
+                    return Err(TwoFACodeStoreError::TooManyAttempts);
+                }
+
                 let data: TwoFATuple = serde_json::from_str(&value)
                     .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
 
@@ -80,6 +94,67 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+
+        // The counter shouldn't outlive the code it belongs to
+        let ttl: i64 = conn.ttl(get_key(email))
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        if ttl <= 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        let (attempts,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(get_attempts_key(email), 1)
+            .expire(get_attempts_key(email), ttl)
+            .ignore()
+            .query(&mut *conn)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        match attempts >= MAX_2FA_ATTEMPTS {
+            true => Err(TwoFACodeStoreError::TooManyAttempts),
+            false => Ok(()),
+        }
+    }
+
+    async fn resend_code(&mut self, email: &Email, code: TwoFACode) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let value: String = conn.get(get_key(email))
+            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let data: TwoFATuple = serde_json::from_str(&value)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        let (resends,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(get_resends_key(email), 1)
+            .expire(get_resends_key(email), TEN_MINUTES_IN_SECONDS as i64)
+            .ignore()
+            .query(&mut *conn)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        if resends > MAX_2FA_RESENDS {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+
+        let serialized_data = serde_json::to_string(&TwoFATuple(data.0, code.as_ref().to_owned()))
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        // The failed attempts of the login attempt have to live as long as its new code
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(get_key(email), serialized_data, TEN_MINUTES_IN_SECONDS)
+            .expire(get_attempts_key(email), TEN_MINUTES_IN_SECONDS as i64)
+            .ignore()
+            .query(&mut *conn)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -87,7 +162,17 @@ struct TwoFATuple(pub String, pub String);
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const TWO_FA_RESENDS_PREFIX: &str = "two_fa_resends:";
 
 fn get_key(email: &Email) -> String {
     format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref())
 }
+
+fn get_attempts_key(email: &Email) -> String {
+    format!("{}{}", TWO_FA_ATTEMPTS_PREFIX, email.as_ref())
+}
+
+fn get_resends_key(email: &Email) -> String {
+    format!("{}{}", TWO_FA_RESENDS_PREFIX, email.as_ref())
+}
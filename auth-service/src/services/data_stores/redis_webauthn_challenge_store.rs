@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::data_stores::{WebAuthnChallenge, WebAuthnChallengeStore, WebAuthnChallengeStoreError},
+    utils::auth::WEBAUTHN_CHALLENGE_TTL_SECONDS,
+};
+
+pub struct RedisWebAuthnChallengeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisWebAuthnChallengeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for RedisWebAuthnChallengeStore {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge) -> Result<(), WebAuthnChallengeStoreError> {
+        let serialized_data = serde_json::to_string(&challenge)
+            .map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let _: () = self.conn.write().await
+            .set_ex(get_key(&challenge.challenge), serialized_data, WEBAUTHN_CHALLENGE_TTL_SECONDS as u64)
+            .map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn consume_challenge(
+        &mut self,
+        challenge: &str,
+    ) -> Result<WebAuthnChallenge, WebAuthnChallengeStoreError> {
+        // GETDEL reads and removes the challenge in one step, so it can't be answered twice concurrently
+        let value: Option<String> = self.conn.write().await
+            .get_del(get_key(challenge))
+            .map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)?;
+
+        serde_json::from_str(&value).map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)
+    }
+}
+
+const WEBAUTHN_CHALLENGE_KEY_PREFIX: &str = "webauthn_challenge:";
+
+fn get_key(challenge: &str) -> String {
+    format!("{}{}", WEBAUTHN_CHALLENGE_KEY_PREFIX, challenge)
+}
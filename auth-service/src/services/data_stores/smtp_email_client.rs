@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::domain::{Email, EmailClient, EmailClientError, EmailMessage};
+
+// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpTls {
+    // Plain text only, for local relays and test servers
+    None,
+    // Connects in plain text and upgrades the connection before anything is sent
+    StartTls,
+    // TLS from the first byte
+    Implicit,
+}
+
+impl SmtpTls {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(SmtpTls::None),
+            "starttls" => Ok(SmtpTls::StartTls),
+            "tls" | "implicit" => Ok(SmtpTls::Implicit),
+            _ => Err("SMTP TLS mode must be one of none, starttls or tls".to_string()),
+        }
+    }
+
+    // Port the mode is usually served on
+    pub fn default_port(&self) -> u16 {
+        match self {
+            SmtpTls::None => 25,
+            SmtpTls::StartTls => 587,
+            SmtpTls::Implicit => 465,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Mailbox emails are sent from, e.g. `Auth Service <no-reply@example.com>`
+    pub from: String,
+    // Limits connecting as well as sending a whole email
+    pub timeout: Duration,
+}
+
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Mailbox,
+    timeout: Duration,
+}
+
+impl SmtpEmailClient {
+    pub fn new(config: &SmtpConfig) -> Result<Self, EmailClientError> {
+        let sender = config.from
+            .parse::<Mailbox>()
+            .map_err(|e| EmailClientError::InvalidMessage(format!("Invalid sender: {}", e)))?;
+
+        let builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                .map_err(map_smtp_error)?,
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .map_err(map_smtp_error)?,
+        };
+
+        let mut builder = builder
+            .port(config.port)
+            .timeout(Some(config.timeout));
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpEmailClient { transport: builder.build(), sender, timeout: config.timeout })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError> {
+        let recipient = recipient
+            .as_ref()
+            .parse::<Mailbox>()
+            .map_err(|e| EmailClientError::InvalidMessage(format!("Invalid recipient: {}", e)))?;
+
+        let message = Message::builder()
+            .from(self.sender.clone())
+            .to(recipient)
+            .subject(&message.subject)
+            .multipart(MultiPart::alternative_plain_html(message.text.clone(), message.html.clone()))
+            .map_err(|e| EmailClientError::InvalidMessage(e.to_string()))?;
+
+        // The transport only limits connecting, a server that stops answering afterwards would hang forever
+        match tokio::time::timeout(self.timeout, self.transport.send(message)).await {
+            Ok(result) => result.map(|_| ()).map_err(map_smtp_error),
+            Err(_) => Err(EmailClientError::Unavailable("Timed out sending email".to_string())),
+        }
+    }
+}
+
+// Only a permanent (5xx) reply means the server won't ever take the message,
+// timeouts, dropped connections and TLS problems may well be gone on the next try
+fn map_smtp_error(error: lettre::transport::smtp::Error) -> EmailClientError {
+    match error.is_permanent() {
+        true => EmailClientError::Rejected(error.to_string()),
+        false => EmailClientError::Unavailable(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tls() {
+        assert_eq!(SmtpTls::parse("none"), Ok(SmtpTls::None));
+        assert_eq!(SmtpTls::parse(" STARTTLS "), Ok(SmtpTls::StartTls));
+        assert_eq!(SmtpTls::parse("tls"), Ok(SmtpTls::Implicit));
+        assert_eq!(SmtpTls::parse("implicit"), Ok(SmtpTls::Implicit));
+        assert!(SmtpTls::parse("ssl3").is_err());
+    }
+
+    #[test]
+    fn test_new_with_invalid_sender() {
+        let config = SmtpConfig {
+            host: "localhost".to_owned(),
+            port: 25,
+            tls: SmtpTls::None,
+            username: None,
+            password: None,
+            from: "not an address".to_owned(),
+            timeout: Duration::from_secs(1),
+        };
+
+        assert!(matches!(SmtpEmailClient::new(&config), Err(EmailClientError::InvalidMessage(_))));
+    }
+}
@@ -0,0 +1,268 @@
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::app_state::{EmailClientType, EmailOutboxStoreType};
+use crate::domain::{Email, EmailClientError, EmailOutboxStoreError, OutboxEmail};
+
+// This value determines how many times delivering an email is tried before it's moved to the dead letters
+pub const MAX_EMAIL_DELIVERY_ATTEMPTS: u32 = 10;
+
+// This value determines how long to wait before retrying a failed delivery, every further failure doubles it
+pub const EMAIL_RETRY_BASE_SECONDS: i64 = 30;
+
+// This value determines the longest wait between two delivery attempts
+pub const EMAIL_RETRY_MAX_SECONDS: i64 = 3600; // 1 hour
+
+// This value determines how long a claimed email is hidden from other workers,
+// it has to outlast the email client's own timeouts and retries
+pub const EMAIL_LEASE_SECONDS: i64 = 300; // 5 minutes
+
+// This value determines how many emails are claimed at once
+const EMAIL_BATCH_SIZE: usize = 20;
+
+// How long to wait before the next attempt, `None` once the email ran out of attempts
+pub fn email_retry_delay_seconds(attempts: u32) -> Option<i64> {
+    if attempts >= MAX_EMAIL_DELIVERY_ATTEMPTS {
+        return None;
+    }
+
+    let doublings = attempts.saturating_sub(1).min(32);
+    Some(EMAIL_RETRY_BASE_SECONDS.saturating_mul(1 << doublings).min(EMAIL_RETRY_MAX_SECONDS))
+}
+
+// Delivers the emails queued in the outbox in the background.
+// Delivery is at least once: an email is only marked as sent after the provider accepted it,
+// so a worker dying in between means the email is sent again once its lease ends.
+pub struct EmailOutboxWorker {
+    email_outbox: EmailOutboxStoreType,
+    email_client: EmailClientType,
+    poll_interval: Duration,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(email_outbox: EmailOutboxStoreType, email_client: EmailClientType, poll_interval: Duration) -> Self {
+        Self { email_outbox, email_client, poll_interval }
+    }
+
+    pub async fn run(self) {
+        loop {
+            // A full batch means there may be more emails due right away
+            match self.process_due().await {
+                Ok(count) if count == EMAIL_BATCH_SIZE => continue,
+                Ok(_) => (),
+                Err(_) => eprintln!("Failed to process the email outbox"),
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    // Tries to deliver every email that is due, returns how many were claimed
+    pub async fn process_due(&self) -> Result<usize, EmailOutboxStoreError> {
+        let now = Utc::now().timestamp();
+
+        // The store isn't locked while sending, so slow deliveries don't hold up queueing new emails
+        let emails = self.email_outbox
+            .write()
+            .await
+            .claim_due(now, now + EMAIL_LEASE_SECONDS, EMAIL_BATCH_SIZE)
+            .await?;
+
+        // An email whose outcome couldn't be recorded is tried again once its lease ends,
+        // that's no reason to hold back the rest of the batch
+        for email in &emails {
+            if self.deliver(email).await.is_err() {
+                eprintln!("Failed to record the delivery of email {}", email.id);
+            }
+        }
+
+        Ok(emails.len())
+    }
+
+    async fn deliver(&self, email: &OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        let result = match Email::parse(&email.recipient) {
+            Ok(recipient) => self.email_client.send_email(&recipient, &email.message).await,
+            Err(_) => Err(EmailClientError::InvalidMessage("Invalid recipient".to_owned())),
+        };
+
+        let mut email_outbox = self.email_outbox.write().await;
+
+        match result {
+            Ok(()) => email_outbox.mark_sent(&email.id).await,
+            Err(error) => {
+                let retry_at = match error.is_retryable() {
+                    true => email_retry_delay_seconds(email.attempts).map(|delay| Utc::now().timestamp() + delay),
+                    false => None,
+                };
+
+                email_outbox.mark_failed(&email.id, &format!("{:?}", error), retry_at).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::{Mutex, RwLock};
+
+    use crate::domain::{EmailClient, EmailMessage, EmailOutboxStore, OutboxEmailStatus};
+    use crate::services::data_stores::HashmapEmailOutboxStore;
+
+    use super::*;
+
+    // Fails with the queued results first, then accepts every email
+    #[derive(Default)]
+    struct ScriptedEmailClient {
+        failures: Mutex<Vec<EmailClientError>>,
+        sent: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for ScriptedEmailClient {
+        async fn send_email(&self, recipient: &Email, _message: &EmailMessage) -> Result<(), EmailClientError> {
+            if let Some(error) = self.failures.lock().await.pop() {
+                return Err(error);
+            }
+
+            self.sent.lock().await.push(recipient.as_ref().to_owned());
+            Ok(())
+        }
+    }
+
+    async fn worker_with_email(failures: Vec<EmailClientError>)
+        -> (EmailOutboxWorker, Arc<RwLock<HashmapEmailOutboxStore>>, Arc<ScriptedEmailClient>) {
+        let email_outbox = Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+        let email_client = Arc::new(ScriptedEmailClient { failures: Mutex::new(failures), ..Default::default() });
+
+        let message = EmailMessage {
+            subject: "Subject".to_owned(),
+            html: "<p>Body</p>".to_owned(),
+            text: "Body".to_owned(),
+        };
+        let email = OutboxEmail::new(&recipient(), message, Utc::now().timestamp());
+        email_outbox.write().await.enqueue(email).await.unwrap();
+
+        let worker = EmailOutboxWorker::new(email_outbox.clone(), email_client.clone(), Duration::from_secs(1));
+
+        (worker, email_outbox, email_client)
+    }
+
+    fn recipient() -> Email {
+        Email::parse("user@example.com").unwrap()
+    }
+
+    async fn outbox_email(email_outbox: &Arc<RwLock<HashmapEmailOutboxStore>>) -> OutboxEmail {
+        email_outbox.read().await.get_emails(&recipient()).await.unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_email_retry_delay_seconds() {
+        assert_eq!(email_retry_delay_seconds(1), Some(EMAIL_RETRY_BASE_SECONDS));
+        assert_eq!(email_retry_delay_seconds(2), Some(EMAIL_RETRY_BASE_SECONDS * 2));
+        assert_eq!(email_retry_delay_seconds(3), Some(EMAIL_RETRY_BASE_SECONDS * 4));
+        assert_eq!(email_retry_delay_seconds(MAX_EMAIL_DELIVERY_ATTEMPTS - 1), Some(EMAIL_RETRY_MAX_SECONDS));
+        assert_eq!(email_retry_delay_seconds(MAX_EMAIL_DELIVERY_ATTEMPTS), None);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_email_once() {
+        let (worker, email_outbox, email_client) = worker_with_email(vec![]).await;
+
+        assert_eq!(worker.process_due().await, Ok(1));
+        assert_eq!(worker.process_due().await, Ok(0));
+
+        assert_eq!(*email_client.sent.lock().await, vec!["user@example.com".to_owned()]);
+        assert_eq!(outbox_email(&email_outbox).await.status, OutboxEmailStatus::Sent);
+    }
+
+    #[tokio::test]
+    async fn test_retry_unavailable_provider_later() {
+        let (worker, email_outbox, email_client) =
+            worker_with_email(vec![EmailClientError::Unavailable("Timed out".to_owned())]).await;
+
+        assert_eq!(worker.process_due().await, Ok(1));
+
+        let email = outbox_email(&email_outbox).await;
+
+        assert_eq!(email.status, OutboxEmailStatus::Pending);
+        assert_eq!(email.attempts, 1);
+        assert!(email.next_attempt_at > Utc::now().timestamp());
+        assert!(email.last_error.unwrap().contains("Timed out"));
+        assert!(email_client.sent.lock().await.is_empty());
+
+        // Not due before the backoff ends
+        assert_eq!(worker.process_due().await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_rejected_email() {
+        let (worker, email_outbox, _) =
+            worker_with_email(vec![EmailClientError::Rejected("Inactive recipient".to_owned())]).await;
+
+        assert_eq!(worker.process_due().await, Ok(1));
+
+        assert_eq!(outbox_email(&email_outbox).await.status, OutboxEmailStatus::Dead);
+    }
+
+    // Deletes the emails of the recipient while sending to them, like an account deleted mid-batch
+    struct DeletingEmailClient {
+        email_outbox: Arc<RwLock<HashmapEmailOutboxStore>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for DeletingEmailClient {
+        async fn send_email(&self, recipient: &Email, _message: &EmailMessage) -> Result<(), EmailClientError> {
+            if recipient.as_ref() == "deleted@example.com" {
+                self.email_outbox.write().await.delete_emails(recipient).await.unwrap();
+            }
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deliver_rest_of_batch_after_store_error() {
+        let email_outbox = Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+        let email_client = Arc::new(DeletingEmailClient { email_outbox: email_outbox.clone() });
+
+        // The email that can't be marked as sent is due first
+        let now = Utc::now().timestamp();
+
+        for (address, due_at) in [("deleted@example.com", now - 1), ("user@example.com", now)] {
+            let message = EmailMessage {
+                subject: "Subject".to_owned(),
+                html: "<p>Body</p>".to_owned(),
+                text: "Body".to_owned(),
+            };
+            let email = OutboxEmail::new(&Email::parse(address).unwrap(), message, due_at);
+            email_outbox.write().await.enqueue(email).await.unwrap();
+        }
+
+        let worker = EmailOutboxWorker::new(email_outbox.clone(), email_client, Duration::from_secs(1));
+
+        assert_eq!(worker.process_due().await, Ok(2));
+
+        assert_eq!(outbox_email(&email_outbox).await.status, OutboxEmailStatus::Sent);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_email_out_of_attempts() {
+        let failures = vec![EmailClientError::Unavailable("Timed out".to_owned()); MAX_EMAIL_DELIVERY_ATTEMPTS as usize];
+        let (worker, email_outbox, email_client) = worker_with_email(failures).await;
+
+        for attempt in 1..=MAX_EMAIL_DELIVERY_ATTEMPTS {
+            // Skip the backoff
+            let email = outbox_email(&email_outbox).await;
+            email_outbox.write().await.mark_failed(&email.id, "Timed out", Some(0)).await.unwrap();
+
+            assert_eq!(worker.process_due().await, Ok(1));
+            assert_eq!(outbox_email(&email_outbox).await.attempts, attempt);
+        }
+
+        assert_eq!(outbox_email(&email_outbox).await.status, OutboxEmailStatus::Dead);
+        assert!(email_client.sent.lock().await.is_empty());
+    }
+}
@@ -1 +1,4 @@
 pub mod data_stores;
+mod email_outbox_worker;
+
+pub use email_outbox_worker::*;
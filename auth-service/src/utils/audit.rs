@@ -0,0 +1,20 @@
+use chrono::Utc;
+
+use crate::AppState;
+use crate::domain::{AuditEvent, AuditEventType, Email};
+
+use super::client_info::ClientInfo;
+
+// Adds an event to the user's audit trail. What it records has already happened by then, so the
+// request doesn't fail if the event can't be stored.
+pub async fn record_audit_event(state: &AppState, email: &Email, event_type: AuditEventType, client: &ClientInfo) {
+    let event = AuditEvent::new(
+        email,
+        event_type,
+        client.ip.clone(),
+        client.user_agent.clone(),
+        Utc::now().timestamp(),
+    );
+
+    let _ = state.audit_log_store.write().await.add_event(event).await;
+}
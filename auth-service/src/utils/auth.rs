@@ -1,18 +1,129 @@
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
 use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, Header, Validation};
 use serde::{Deserialize, Serialize};
-use crate::app_state::BannedTokenStoreType;
+use time::Duration;
+use uuid::Uuid;
+use crate::AppState;
+use crate::app_state::{BannedTokenStoreType, RefreshTokenStoreType, SessionStoreType, UserStoreType};
 use crate::domain::email::Email;
+use crate::domain::{
+    AccountStatus, AuthAPIError, LoginAttemptKey, RefreshToken, RefreshTokenRecord, Session, SessionStoreError, User,
+    UserStoreError,
+};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::client_info::ClientInfo;
+use super::constants::{
+    JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_DEFAULT_ROLES, JWT_ISSUER, JWT_KEYRING, REFRESH_TOKEN_COOKIE_NAME,
+    TOKEN_STATUS_CHECK,
+};
+
+// Create cookie with a new JWT auth token and record the token in the session registry
+pub async fn generate_auth_cookie(
+    user: &User,
+    client: &ClientInfo,
+    refresh_family_id: Option<String>,
+    session_store: SessionStoreType,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let (token, claims) = generate_auth_token(user)?;
+
+    let now = Utc::now().timestamp();
+
+    // A session that can be renewed lasts as long as its refresh token, so every session still able to
+    // get new tokens is listed and can be revoked. Rotation replaces the session, extending it again.
+    let expires_at = match refresh_family_id {
+        Some(_) => now + REFRESH_TOKEN_TTL_SECONDS,
+        None => claims.exp as i64,
+    };
+
+    let session = Session {
+        id: claims.jti,
+        email: claims.sub,
+        created_at: now,
+        expires_at,
+        user_agent: client.user_agent.clone(),
+        ip: client.ip.clone(),
+        refresh_family_id,
+    };
+
+    session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
 
-// Create cookie with a new JWT auth token
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_auth_token(email)?;
     Ok(create_auth_cookie(token))
 }
 
+// Remove every session issued from the given refresh token family
+pub async fn remove_family_sessions(
+    email: &Email,
+    family_id: &str,
+    session_store: SessionStoreType,
+) -> Result<(), SessionStoreError> {
+    let mut session_store = session_store.write().await;
+
+    for session in session_store.get_sessions(email).await? {
+        if session.refresh_family_id.as_deref() == Some(family_id) {
+            session_store.remove_session(&session.id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Remove every session of the user and revoke the refresh token families they were issued from
+pub async fn remove_all_sessions(
+    email: &Email,
+    session_store: SessionStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<(), SessionStoreError> {
+    remove_sessions_except(email, None, session_store, refresh_token_store).await
+}
+
+// Same as `remove_all_sessions`, but keeps the session the request was made from
+pub async fn remove_other_sessions(
+    email: &Email,
+    current_session_id: &str,
+    session_store: SessionStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<(), SessionStoreError> {
+    remove_sessions_except(email, Some(current_session_id), session_store, refresh_token_store).await
+}
+
+async fn remove_sessions_except(
+    email: &Email,
+    kept_session_id: Option<&str>,
+    session_store: SessionStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<(), SessionStoreError> {
+    let mut session_store = session_store.write().await;
+
+    for session in session_store.get_sessions(email).await? {
+        if kept_session_id == Some(session.id.as_str()) {
+            continue;
+        }
+
+        session_store.remove_session(&session.id).await?;
+
+        if let Some(family_id) = session.refresh_family_id {
+            refresh_token_store
+                .write()
+                .await
+                .revoke_family(&family_id)
+                .await
+                .map_err(|_| SessionStoreError::UnexpectedError)?;
+        }
+    }
+
+    Ok(())
+}
+
 // Create cookie and set the value to the passed-in token string
 fn create_auth_cookie(token: String) -> Cookie<'static> {
     let cookie = Cookie::build((JWT_COOKIE_NAME, token))
@@ -24,6 +135,34 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+// Create cookie with a new refresh token and persist the token so it can be exchanged once
+pub async fn generate_refresh_cookie(
+    user: &User,
+    family_id: String,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let token = RefreshToken::default();
+
+    refresh_token_store
+        .write()
+        .await
+        .add_token(token.clone(), RefreshTokenRecord::new(user, family_id))
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    Ok(create_refresh_cookie(token.as_ref().to_owned()))
+}
+
+// Create refresh cookie which, unlike the auth cookie, outlives the browser session
+fn create_refresh_cookie(token: String) -> Cookie<'static> {
+    Cookie::build((REFRESH_TOKEN_COOKIE_NAME, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build()
+}
+
 #[derive(Debug)]
 pub enum GenerateTokenError {
     TokenError(jsonwebtoken::errors::Error),
@@ -33,27 +172,116 @@ pub enum GenerateTokenError {
 // This value determines how long the JWT auth token is valid for
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
+// This value determines how long a refresh token can be exchanged for a new JWT auth token
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 1_209_600; // 14 days
+
+// This value determines how long an emailed password reset token can be used for
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = 1800; // 30 minutes
+
+// This value determines how long an emailed verification link can be used for
+pub const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: i64 = 86_400; // 24 hours
+
+// This value determines how long a user has to wait before another verification email is sent
+pub const EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS: i64 = 60; // 1 minute
+
+// This value determines how long a WebAuthn ceremony can take between its begin and finish requests
+pub const WEBAUTHN_CHALLENGE_TTL_SECONDS: i64 = 300; // 5 minutes
+
+// This value determines how many wrong 2FA codes can be entered before the login attempt is invalidated
+pub const MAX_2FA_ATTEMPTS: u32 = 5;
+
+// This value determines how many times the 2FA code of a login attempt can be sent again
+pub const MAX_2FA_RESENDS: u32 = 3;
+
+// This value determines how many wrong passwords in a row lock an account
+pub const MAX_LOGIN_FAILURES_PER_EMAIL: u32 = 5;
+
+// This value determines how many wrong passwords in a row lock an IP address,
+// it's higher than per account since many users can share one address
+pub const MAX_LOGIN_FAILURES_PER_IP: u32 = 20;
+
+// This value determines how long the first lockout lasts, every further failure doubles it
+pub const LOGIN_LOCKOUT_BASE_SECONDS: i64 = 30;
+
+// This value determines the longest a lockout can last
+pub const LOGIN_LOCKOUT_MAX_SECONDS: i64 = 3600; // 1 hour
+
+// This value determines how long failed logins are remembered for when no new ones come in
+pub const LOGIN_FAILURE_TTL_SECONDS: i64 = 86_400; // 24 hours
+
+// How long a key is locked for after its latest failed login, 0 while it's still under the limit
+pub fn login_lockout_seconds(key: &LoginAttemptKey, failures: u32) -> i64 {
+    let max_failures = match key {
+        LoginAttemptKey::Email(_) => MAX_LOGIN_FAILURES_PER_EMAIL,
+        LoginAttemptKey::Ip(_) => MAX_LOGIN_FAILURES_PER_IP,
+    };
+
+    if failures < max_failures {
+        return 0;
+    }
+
+    let doublings = (failures - max_failures).min(32);
+
+    LOGIN_LOCKOUT_BASE_SECONDS
+        .saturating_mul(1 << doublings)
+        .min(LOGIN_LOCKOUT_MAX_SECONDS)
+}
+
+// Roles every user has followed by the ones granted to the user
+pub fn user_roles(user: &User) -> Vec<String> {
+    let mut roles = JWT_DEFAULT_ROLES.to_owned();
+
+    for role in &user.roles {
+        if !roles.contains(role) {
+            roles.push(role.clone());
+        }
+    }
+
+    roles
+}
+
 // Create JWT auth token
-fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
+fn generate_auth_token(user: &User) -> Result<(String, Claims), GenerateTokenError> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .ok_or(GenerateTokenError::UnexpectedError)?;
 
+    let now = Utc::now();
+
     // Create JWT expiration time
-    let exp = Utc::now()
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(GenerateTokenError::UnexpectedError)?
         .timestamp();
 
-    // Cast exp to a usize, which is what Claims expects
+    // Cast timestamps to a usize, which is what Claims expects
     let exp: usize = exp
         .try_into()
         .map_err(|_| GenerateTokenError::UnexpectedError)?;
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    let sub = user.email.as_ref().to_owned();
 
-    let sub = email.as_ref().to_owned();
+    // Every token gets its own id, which is also the id of the session it belongs to
+    let jti = Uuid::new_v4().to_string();
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        iss: JWT_ISSUER.to_owned(),
+        sub,
+        aud: JWT_AUDIENCE.to_owned(),
+        exp,
+        nbf: iat,
+        iat,
+        jti,
+        roles: user_roles(user),
+        token_version: user.token_version,
+    };
 
-    create_token(&claims).map_err(GenerateTokenError::TokenError)
+    let token = create_token(&claims).map_err(GenerateTokenError::TokenError)?;
+
+    Ok((token, claims))
 }
 
 // Check if JWT auth token is valid by decoding it using the JWT secret
@@ -90,6 +318,8 @@ fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
 pub async fn validate_token(
     token: &str,
     banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
 ) -> Result<Claims, jsonwebtoken::errors::Error> {
     match banned_token_store.read().await.contains_token(token).await {
         Ok(value) => {
@@ -106,45 +336,175 @@ pub async fn validate_token(
         }
     }
 
-    decode::<Claims>(
+    // The key is picked by the `kid` header, so tokens signed before a key rotation stay valid
+    let header = decode_header(token)?;
+    let key = JWT_KEYRING.verification_key(header.kid.as_deref()).ok_or_else(|| {
+        jsonwebtoken::errors::Error::from(jsonwebtoken::errors::ErrorKind::InvalidSignature)
+    })?;
+
+    let claims = decode::<Claims>(
         token,
-        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
-        &Validation::default(),
+        key.decoding_key(),
+        &token_validation(key.algorithm()),
     )
-        .map(|data| data.claims)
+        .map(|data| data.claims)?;
+
+    // A token is only accepted while its session hasn't been revoked
+    if session_store.read().await.get_session(&claims.jti).await.is_err() {
+        return Err(jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidToken,
+        ));
+    }
+
+    let email = Email::parse(&claims.sub).map_err(|_| {
+        jsonwebtoken::errors::Error::from(jsonwebtoken::errors::ErrorKind::InvalidSubject)
+    })?;
+
+    match user_store.read().await.get_user(&email).await {
+        Ok(user) if token_accepted_for(&user, &claims, *TOKEN_STATUS_CHECK) => Ok(claims),
+        _ => Err(jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidToken,
+        )),
+    }
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
+// Tokens issued before the user logged out everywhere carry an outdated version.
+// With `check_status`, tokens of accounts that aren't active anymore are turned away too.
+fn token_accepted_for(user: &User, claims: &Claims, check_status: bool) -> bool {
+    user.token_version == claims.token_version && (!check_status || user.status == AccountStatus::Active)
+}
+
+// Tokens are only accepted from this issuer and for the configured audience
+fn token_validation(algorithm: Algorithm) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&JWT_AUDIENCE);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+    validation
+}
+
+// Create JWT auth token by encoding claims using the active key of the keyring
 fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
-    )
+    let (kid, key) = JWT_KEYRING.active();
+
+    let mut header = Header::new(key.algorithm());
+    header.kid = Some(kid.to_owned());
+
+    encode(&header, &claims, key.encoding_key())
+}
+
+// Extracts the user the valid `jwt` cookie was issued to, along with the token's claims
+//
+//     async fn handler(AuthenticatedUser { user, claims }: AuthenticatedUser) { ... }
+pub struct AuthenticatedUser {
+    pub user: User,
+    pub claims: Claims,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let jar = CookieJar::from_headers(&parts.headers);
+        let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
+
+        let claims = validate_token(
+            cookie.value(),
+            state.banned_token_store.clone(),
+            state.session_store.clone(),
+            state.user_store.clone(),
+        )
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        let email = Email::parse(&claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+        let user = match state.user_store.read().await.get_user(&email).await {
+            Ok(user) => user,
+            Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+            Err(_) => return Err(AuthAPIError::UnexpectedError),
+        };
+
+        Ok(AuthenticatedUser { user, claims })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
+    pub iss: String,
     pub sub: String,
+    pub aud: Vec<String>,
     pub exp: usize,
+    pub nbf: usize,
+    pub iat: usize,
+    pub jti: String,
+    pub roles: Vec<String>,
+    pub token_version: i32,
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
     use tokio::sync::RwLock;
-    use crate::HashsetBannedTokenStore;
+    use crate::{HashmapRefreshTokenStore, HashmapSessionStore, HashmapUserStore, HashsetBannedTokenStore};
+    use crate::domain::{Password, RefreshTokenStore, SessionStore, UserStore};
     use super::*;
 
+    fn test_user() -> User {
+        User::new(
+            Email::parse("test@example.com").unwrap(),
+            Password::parse("password123").unwrap(),
+            false,
+        )
+    }
+
+    async fn test_user_store() -> UserStoreType {
+        let mut user_store = HashmapUserStore::default();
+        user_store.add_user(test_user()).await.unwrap();
+        // Tokens are only accepted for active accounts
+        user_store.mark_email_verified(&test_user().email).await.unwrap();
+        Arc::new(RwLock::new(user_store))
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse("test@example.com").unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let user = test_user();
+        let client = ClientInfo {
+            user_agent: Some("test-agent".to_owned()),
+            ip: Some("127.0.0.1".to_owned()),
+        };
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let cookie = generate_auth_cookie(&user, &client, None, session_store.clone())
+            .await
+            .unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+
+        let sessions = session_store.read().await.get_sessions(&user.email).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].user_agent, client.user_agent);
+        assert_eq!(sessions[0].ip, client.ip);
+        assert!(sessions[0].expires_at <= Utc::now().timestamp() + TOKEN_TTL_SECONDS);
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_cookie_with_refresh_family() {
+        let user = test_user();
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        generate_auth_cookie(&user, &ClientInfo::default(), Some("family".to_owned()), session_store.clone())
+            .await
+            .unwrap();
+
+        // The session outlives its access token, as the refresh token can still renew it
+        let sessions = session_store.read().await.get_sessions(&user.email).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].expires_at > Utc::now().timestamp() + TOKEN_TTL_SECONDS);
+        assert!(sessions[0].expires_at <= Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS);
     }
 
     #[tokio::test]
@@ -158,20 +518,120 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let user = test_user();
+        let refresh_token_store =
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let cookie = generate_refresh_cookie(&user, "family".to_owned(), refresh_token_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.max_age(), Some(Duration::seconds(REFRESH_TOKEN_TTL_SECONDS)));
+        assert_eq!(cookie.http_only(), Some(true));
+
+        let token = RefreshToken::parse(cookie.value().to_owned()).unwrap();
+        let record = refresh_token_store.write().await.consume_token(&token).await.unwrap();
+        assert_eq!(record.email, "test@example.com");
+        assert_eq!(record.family_id, "family");
+    }
+
+    #[tokio::test]
+    async fn test_remove_all_sessions() {
+        let user = test_user();
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let refresh_token_store =
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+
+        generate_auth_cookie(&user, &ClientInfo::default(), Some("family".to_owned()), session_store.clone())
+            .await
+            .unwrap();
+        generate_auth_cookie(&user, &ClientInfo::default(), None, session_store.clone())
+            .await
+            .unwrap();
+        let cookie = generate_refresh_cookie(&user, "family".to_owned(), refresh_token_store.clone())
+            .await
+            .unwrap();
+
+        remove_all_sessions(&user.email, session_store.clone(), refresh_token_store.clone())
+            .await
+            .unwrap();
+
+        assert!(session_store.read().await.get_sessions(&user.email).await.unwrap().is_empty());
+
+        let token = RefreshToken::parse(cookie.value().to_owned()).unwrap();
+        assert!(refresh_token_store.write().await.consume_token(&token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remove_other_sessions() {
+        let user = test_user();
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let refresh_token_store =
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+
+        generate_auth_cookie(&user, &ClientInfo::default(), None, session_store.clone())
+            .await
+            .unwrap();
+        let (_, claims) = generate_auth_token(&user).unwrap();
+        let current = Session {
+            id: claims.jti.clone(),
+            email: claims.sub,
+            created_at: claims.iat as i64,
+            expires_at: claims.exp as i64,
+            user_agent: None,
+            ip: None,
+            refresh_family_id: None,
+        };
+        session_store.write().await.add_session(current).await.unwrap();
+
+        remove_other_sessions(&user.email, &claims.jti, session_store.clone(), refresh_token_store)
+            .await
+            .unwrap();
+
+        let sessions = session_store.read().await.get_sessions(&user.email).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, claims.jti);
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse("test@example.com").unwrap();
-        let result = generate_auth_token(&email).unwrap();
-        assert_eq!(result.split('.').count(), 3);
+        let (token, claims) = generate_auth_token(&test_user()).unwrap();
+        assert_eq!(token.split('.').count(), 3);
+        assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some(JWT_KEYRING.active().0));
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.iss, *JWT_ISSUER);
+        assert_eq!(claims.aud, *JWT_AUDIENCE);
+        assert_eq!(claims.roles, *JWT_DEFAULT_ROLES);
+        assert_eq!(claims.iat, claims.nbf);
+        assert_eq!(claims.exp, claims.iat + TOKEN_TTL_SECONDS as usize);
+        assert_eq!(claims.token_version, 0);
+        assert!(Uuid::parse_str(&claims.jti).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_token_with_granted_roles() {
+        let mut user = test_user();
+        user.roles = vec!["admin".to_owned(), JWT_DEFAULT_ROLES[0].clone()];
+
+        let (_, claims) = generate_auth_token(&user).unwrap();
+
+        let mut expected = JWT_DEFAULT_ROLES.to_owned();
+        expected.push("admin".to_owned());
+        assert_eq!(claims.roles, expected);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse("test@example.com").unwrap();
-        let token = generate_auth_token(&email).unwrap();
         let banned_token_store =
             Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await.unwrap();
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let cookie = generate_auth_cookie(&test_user(), &ClientInfo::default(), None, session_store.clone())
+            .await
+            .unwrap();
+        let result = validate_token(cookie.value(), banned_token_store, session_store, test_user_store().await)
+            .await
+            .unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -187,7 +647,117 @@ mod tests {
         let token = "invalid_token".to_owned();
         let banned_token_store =
             Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let result = validate_token(&token, banned_token_store, session_store, test_user_store().await).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_session() {
+        let banned_token_store =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = test_user_store().await;
+        let cookie = generate_auth_cookie(&test_user(), &ClientInfo::default(), None, session_store.clone())
+            .await
+            .unwrap();
+
+        let claims = validate_token(cookie.value(), banned_token_store.clone(), session_store.clone(), user_store.clone())
+            .await
+            .unwrap();
+        session_store.write().await.remove_session(&claims.jti).await.unwrap();
+
+        let result = validate_token(cookie.value(), banned_token_store, session_store, user_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_outdated_token_version() {
+        let banned_token_store =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = test_user_store().await;
+        let cookie = generate_auth_cookie(&test_user(), &ClientInfo::default(), None, session_store.clone())
+            .await
+            .unwrap();
+
+        user_store.write().await.increment_token_version(&test_user().email).await.unwrap();
+
+        let result = validate_token(cookie.value(), banned_token_store, session_store, user_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_of_suspended_user() {
+        let banned_token_store =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = test_user_store().await;
+        let cookie = generate_auth_cookie(&test_user(), &ClientInfo::default(), None, session_store.clone())
+            .await
+            .unwrap();
+
+        user_store.write().await.set_status(&test_user().email, AccountStatus::Suspended).await.unwrap();
+
+        let result = validate_token(cookie.value(), banned_token_store, session_store, user_store).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_token_accepted_for() {
+        let (_, claims) = generate_auth_token(&test_user()).unwrap();
+        let active = User { status: AccountStatus::Active, ..test_user() };
+        let suspended = User { status: AccountStatus::Suspended, ..test_user() };
+
+        assert!(token_accepted_for(&active, &claims, true));
+        assert!(!token_accepted_for(&suspended, &claims, true));
+        assert!(token_accepted_for(&suspended, &claims, false));
+
+        let outdated = User { token_version: 1, ..active };
+        assert!(!token_accepted_for(&outdated, &claims, false));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_foreign_issuer_or_audience() {
+        let banned_token_store =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = test_user_store().await;
+
+        let (_, claims) = generate_auth_token(&test_user()).unwrap();
+        let foreign_issuer = Claims { iss: "another-service".to_owned(), ..claims };
+        let (_, claims) = generate_auth_token(&test_user()).unwrap();
+        let foreign_audience = Claims { aud: vec!["another-service".to_owned()], ..claims };
+
+        for claims in [foreign_issuer, foreign_audience] {
+            let token = create_token(&claims).unwrap();
+            let session = Session {
+                id: claims.jti.clone(),
+                email: claims.sub.clone(),
+                created_at: claims.iat as i64,
+                expires_at: claims.exp as i64,
+                user_agent: None,
+                ip: None,
+                refresh_family_id: None,
+            };
+            session_store.write().await.add_session(session).await.unwrap();
+
+            let result = validate_token(&token, banned_token_store.clone(), session_store.clone(), user_store.clone()).await;
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_login_lockout_seconds() {
+        let email = LoginAttemptKey::Email(Email::parse("test@example.com").unwrap());
+        let ip = LoginAttemptKey::Ip("127.0.0.1".to_owned());
+
+        assert_eq!(login_lockout_seconds(&email, MAX_LOGIN_FAILURES_PER_EMAIL - 1), 0);
+        assert_eq!(login_lockout_seconds(&email, MAX_LOGIN_FAILURES_PER_EMAIL), LOGIN_LOCKOUT_BASE_SECONDS);
+        assert_eq!(login_lockout_seconds(&email, MAX_LOGIN_FAILURES_PER_EMAIL + 2), LOGIN_LOCKOUT_BASE_SECONDS * 4);
+        assert_eq!(login_lockout_seconds(&email, u32::MAX), LOGIN_LOCKOUT_MAX_SECONDS);
+
+        assert_eq!(login_lockout_seconds(&ip, MAX_LOGIN_FAILURES_PER_EMAIL), 0);
+        assert_eq!(login_lockout_seconds(&ip, MAX_LOGIN_FAILURES_PER_IP), LOGIN_LOCKOUT_BASE_SECONDS);
+    }
 }
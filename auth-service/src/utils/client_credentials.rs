@@ -0,0 +1,75 @@
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::domain::AuthAPIError;
+
+use super::constant_time::constant_time_eq;
+use super::constants::{INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET};
+
+// Service allowed to introspect tokens, authenticated with HTTP Basic client credentials
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntrospectionClient {
+    pub client_id: String,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IntrospectionClient
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let (client_id, client_secret) = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_basic_credentials)
+            .ok_or(AuthAPIError::InvalidClientCredentials)?;
+
+        // Introspection stays disabled until a client secret is configured
+        let expected_secret = INTROSPECTION_CLIENT_SECRET
+            .as_ref()
+            .ok_or(AuthAPIError::InvalidClientCredentials)?;
+
+        let id_matches = constant_time_eq(client_id.as_bytes(), INTROSPECTION_CLIENT_ID.as_bytes());
+        let secret_matches = constant_time_eq(client_secret.as_bytes(), expected_secret.as_bytes());
+
+        if !(id_matches && secret_matches) {
+            return Err(AuthAPIError::InvalidClientCredentials);
+        }
+
+        Ok(IntrospectionClient { client_id })
+    }
+}
+
+// Split an `Authorization: Basic <base64(id:secret)>` header into the client id and secret
+fn parse_basic_credentials(header: &str) -> Option<(String, String)> {
+    let encoded = header.strip_prefix("Basic ")?;
+    let decoded = STANDARD.decode(encoded.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+
+    Some((client_id.to_owned(), client_secret.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic_credentials() {
+        let header = format!("Basic {}", STANDARD.encode("app-service:s3cr:et"));
+        assert_eq!(
+            parse_basic_credentials(&header),
+            Some(("app-service".to_owned(), "s3cr:et".to_owned()))
+        );
+        assert_eq!(parse_basic_credentials("Bearer token"), None);
+        assert_eq!(parse_basic_credentials("Basic not-base64"), None);
+        assert_eq!(parse_basic_credentials(&format!("Basic {}", STANDARD.encode("no-colon"))), None);
+    }
+}
@@ -0,0 +1,168 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::USER_AGENT;
+use axum::http::request::Parts;
+
+use super::constants::{REAL_IP_HEADER, TRUSTED_PROXIES};
+
+// Details about the client making the request, recorded alongside issued sessions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned());
+
+        let ip = client_ip(parts, &TRUSTED_PROXIES);
+
+        Ok(ClientInfo { user_agent, ip })
+    }
+}
+
+// In production the service sits behind nginx, which passes the client address on. Anyone else
+// could put any address in the header, so it's only taken from the configured proxies.
+fn client_ip(parts: &Parts, trusted_proxies: &[IpRange]) -> Option<String> {
+    let peer = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip())?;
+
+    if !trusted_proxies.iter().any(|proxy| proxy.contains(&peer)) {
+        return Some(peer.to_string());
+    }
+
+    let forwarded_ip = parts
+        .headers
+        .get(REAL_IP_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<IpAddr>().ok());
+
+    Some(forwarded_ip.unwrap_or(peer).to_string())
+}
+
+// Single address or CIDR block, e.g. `172.28.0.10` or `10.0.0.0/8`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (address, prefix_len) = match value.trim().split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (value.trim(), None),
+        };
+
+        let network: IpAddr = address.parse().map_err(|_| "Invalid IP address".to_string())?;
+
+        let max_prefix_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| "Invalid prefix length".to_string())?,
+            None => max_prefix_len,
+        };
+
+        match prefix_len <= max_prefix_len {
+            true => Ok(IpRange { network, prefix_len }),
+            false => Err("Invalid prefix length".to_string()),
+        }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) =>
+                prefix_matches(u32::from(network) as u128, u32::from(*ip) as u128, 32, self.prefix_len),
+            (IpAddr::V6(network), IpAddr::V6(ip)) =>
+                prefix_matches(u128::from(network), u128::from(*ip), 128, self.prefix_len),
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(network: u128, ip: u128, bits: u8, prefix_len: u8) -> bool {
+    let host_bits = (bits - prefix_len) as u32;
+
+    network.checked_shr(host_bits).unwrap_or(0) == ip.checked_shr(host_bits).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    fn request_parts(peer: &str, real_ip: Option<&str>) -> Parts {
+        let mut request = Request::builder();
+
+        if let Some(real_ip) = real_ip {
+            request = request.header(REAL_IP_HEADER, real_ip);
+        }
+
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        parts.extensions.insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+
+        parts
+    }
+
+    #[test]
+    fn test_parse_ip_range() {
+        assert_eq!(IpRange::parse("10.0.0.1").unwrap().prefix_len, 32);
+        assert_eq!(IpRange::parse(" 10.0.0.0/8 ").unwrap().prefix_len, 8);
+        assert_eq!(IpRange::parse("::1").unwrap().prefix_len, 128);
+        assert!(IpRange::parse("10.0.0.0/33").is_err());
+        assert!(IpRange::parse("10.0.0/8").is_err());
+        assert!(IpRange::parse("proxy").is_err());
+    }
+
+    #[test]
+    fn test_ip_range_contains() {
+        let range = IpRange::parse("172.28.0.0/16").unwrap();
+
+        assert!(range.contains(&"172.28.5.1".parse().unwrap()));
+        assert!(!range.contains(&"172.29.0.1".parse().unwrap()));
+        assert!(!range.contains(&"::1".parse().unwrap()));
+
+        assert!(IpRange::parse("0.0.0.0/0").unwrap().contains(&"8.8.8.8".parse().unwrap()));
+        assert!(IpRange::parse("::1").unwrap().contains(&"::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_client_ip_from_trusted_proxy() {
+        let trusted_proxies = vec![IpRange::parse("172.28.0.10").unwrap()];
+
+        let parts = request_parts("172.28.0.10:40000", Some("203.0.113.7"));
+        assert_eq!(client_ip(&parts, &trusted_proxies), Some("203.0.113.7".to_owned()));
+
+        // Without the header the proxy itself is the client
+        let parts = request_parts("172.28.0.10:40000", None);
+        assert_eq!(client_ip(&parts, &trusted_proxies), Some("172.28.0.10".to_owned()));
+    }
+
+    #[test]
+    fn test_client_ip_ignores_header_from_other_clients() {
+        let trusted_proxies = vec![IpRange::parse("172.28.0.10").unwrap()];
+
+        let parts = request_parts("198.51.100.1:40000", Some("203.0.113.7"));
+        assert_eq!(client_ip(&parts, &trusted_proxies), Some("198.51.100.1".to_owned()));
+    }
+}
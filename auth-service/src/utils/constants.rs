@@ -1,10 +1,41 @@
 use dotenvy::dotenv;
+use jsonwebtoken::Algorithm;
 use lazy_static::lazy_static;
+use std::collections::HashMap;
 use std::env as std_env;
+use std::path::PathBuf;
+
+use std::time::Duration;
+
+use crate::domain::{RateLimit, RateLimits};
+use crate::services::data_stores::{HttpEmailConfig, SmtpConfig, SmtpTls};
+
+use super::client_info::IpRange;
+use super::keyring::{Keyring, KeyringConfig};
+use super::signing_key::SigningKey;
+use super::webauthn::RelyingParty;
 
 // Define a lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
 lazy_static! {
     pub static ref JWT_SECRET: String = set_token();
+    pub static ref JWT_KEYRING: Keyring = set_keyring();
+    pub static ref JWT_ISSUER: String = set_jwt_issuer();
+    pub static ref JWT_AUDIENCE: Vec<String> = set_jwt_audience();
+    pub static ref JWT_DEFAULT_ROLES: Vec<String> = set_jwt_default_roles();
+    pub static ref ADMIN_EMAILS: Vec<String> = set_admin_emails();
+    pub static ref INTROSPECTION_CLIENT_ID: String = set_introspection_client_id();
+    pub static ref INTROSPECTION_CLIENT_SECRET: Option<String> = set_introspection_client_secret();
+    pub static ref PASSWORD_RESET_URL: String = set_password_reset_url();
+    pub static ref EMAIL_VERIFICATION_URL: String = set_email_verification_url();
+    pub static ref TOTP_ISSUER: String = set_totp_issuer();
+    pub static ref TOTP_SKEW_STEPS: i64 = set_totp_skew_steps();
+    pub static ref TOKEN_STATUS_CHECK: bool = set_token_status_check();
+    pub static ref WEBAUTHN_RELYING_PARTY: RelyingParty = set_webauthn_relying_party();
+    pub static ref RATE_LIMITS: RateLimits = set_rate_limits();
+    pub static ref TRUSTED_PROXIES: Vec<IpRange> = set_trusted_proxies();
+    pub static ref SMTP_CONFIG: Option<SmtpConfig> = set_smtp_config();
+    pub static ref EMAIL_API_CONFIG: Option<HttpEmailConfig> = set_email_api_config();
+    pub static ref EMAIL_TEMPLATE_DIR: Option<PathBuf> = set_email_template_dir();
     pub static ref DATABASE_URL: String = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
 }
@@ -19,6 +50,228 @@ fn set_token() -> String {
     secret
 }
 
+// Keys are rotated through the keyring file. Without one, the single key configured below is used.
+fn set_keyring() -> Keyring {
+    dotenv().ok();
+    let path = match std_env::var(env::JWT_KEYRING_PATH_ENV_VAR).ok().filter(|path| !path.is_empty()) {
+        Some(path) => path,
+        None => {
+            let kid = std_env::var(env::JWT_KEY_ID_ENV_VAR).unwrap_or(DEFAULT_JWT_KEY_ID.to_owned());
+            return Keyring::new(kid, set_signing_key());
+        }
+    };
+
+    let config = std::fs::read(path).expect("JWT_KEYRING_PATH must point to a readable file.");
+    let config: KeyringConfig = serde_json::from_slice(&config).expect("JWT_KEYRING_PATH must contain a valid keyring.");
+
+    Keyring::from_config(config).expect("JWT_KEYRING_PATH must contain a valid keyring.")
+}
+
+// HS256 with JWT_SECRET stays the default. RS256 and EdDSA sign with the private key from a PEM file.
+fn set_signing_key() -> SigningKey {
+    dotenv().ok();
+    let algorithm = std_env::var(env::JWT_ALGORITHM_ENV_VAR).unwrap_or(DEFAULT_JWT_ALGORITHM.to_owned());
+    let algorithm: Algorithm = algorithm.parse().expect("JWT_ALGORITHM must be a valid algorithm.");
+
+    if algorithm == Algorithm::HS256 {
+        return SigningKey::hs256(JWT_SECRET.as_bytes());
+    }
+
+    let path = std_env::var(env::JWT_PRIVATE_KEY_PATH_ENV_VAR)
+        .expect("JWT_PRIVATE_KEY_PATH must be set.");
+    let pem = std::fs::read(path).expect("JWT_PRIVATE_KEY_PATH must point to a readable file.");
+
+    SigningKey::from_pem(algorithm, &pem)
+        .expect("JWT_PRIVATE_KEY_PATH must contain a private key matching JWT_ALGORITHM.")
+}
+
+fn set_jwt_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_ISSUER_ENV_VAR).unwrap_or(DEFAULT_JWT_ISSUER.to_owned())
+}
+
+// Comma separated list of the services tokens are issued for
+fn set_jwt_audience() -> Vec<String> {
+    dotenv().ok();
+    let audience = std_env::var(env::JWT_AUDIENCE_ENV_VAR).unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned());
+    let audience = split_list(&audience);
+    if audience.is_empty() {
+        panic!("JWT_AUDIENCE must not be empty.");
+    }
+    audience
+}
+
+// Comma separated list of the roles granted to every user
+fn set_jwt_default_roles() -> Vec<String> {
+    dotenv().ok();
+    let roles = std_env::var(env::JWT_DEFAULT_ROLES_ENV_VAR).unwrap_or(DEFAULT_JWT_ROLES.to_owned());
+    split_list(&roles)
+}
+
+// Comma separated list of the users granted the admin role, so the first admin doesn't need another one
+fn set_admin_emails() -> Vec<String> {
+    dotenv().ok();
+    let emails = std_env::var(env::ADMIN_EMAILS_ENV_VAR).unwrap_or_default();
+    split_list(&emails)
+}
+
+fn set_introspection_client_id() -> String {
+    dotenv().ok();
+    std_env::var(env::INTROSPECTION_CLIENT_ID_ENV_VAR).unwrap_or(DEFAULT_INTROSPECTION_CLIENT_ID.to_owned())
+}
+
+// Without a secret no client can authenticate, so /introspect is effectively disabled
+fn set_introspection_client_secret() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::INTROSPECTION_CLIENT_SECRET_ENV_VAR)
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
+
+// Page of the frontend the emailed reset token is appended to
+fn set_password_reset_url() -> String {
+    dotenv().ok();
+    std_env::var(env::PASSWORD_RESET_URL_ENV_VAR).unwrap_or(DEFAULT_PASSWORD_RESET_URL.to_owned())
+}
+
+// Public address of the /verify-email route the emailed token is appended to
+fn set_email_verification_url() -> String {
+    dotenv().ok();
+    std_env::var(env::EMAIL_VERIFICATION_URL_ENV_VAR).unwrap_or(DEFAULT_EMAIL_VERIFICATION_URL.to_owned())
+}
+
+// Account name shown by authenticator apps next to the user's email
+fn set_totp_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::TOTP_ISSUER_ENV_VAR).unwrap_or(DEFAULT_TOTP_ISSUER.to_owned())
+}
+
+// Number of 30 second steps a TOTP code is still accepted before or after its own
+fn set_totp_skew_steps() -> i64 {
+    dotenv().ok();
+    std_env::var(env::TOTP_SKEW_STEPS_ENV_VAR)
+        .map(|steps| steps.parse().expect("TOTP_SKEW_STEPS must be a non-negative number."))
+        .unwrap_or(DEFAULT_TOTP_SKEW_STEPS)
+        .max(0)
+}
+
+// Whether token validation also turns away tokens of accounts that are no longer active,
+// so a user suspended outside of the admin routes loses access at once
+fn set_token_status_check() -> bool {
+    dotenv().ok();
+    std_env::var(env::TOKEN_STATUS_CHECK_ENV_VAR)
+        .map(|check| check.parse().expect("TOKEN_STATUS_CHECK must be true or false."))
+        .unwrap_or(DEFAULT_TOKEN_STATUS_CHECK)
+}
+
+// Passkeys only work on the configured origin, which has to be on the relying party's domain
+fn set_webauthn_relying_party() -> RelyingParty {
+    dotenv().ok();
+    RelyingParty {
+        id: std_env::var(env::WEBAUTHN_RP_ID_ENV_VAR).unwrap_or(DEFAULT_WEBAUTHN_RP_ID.to_owned()),
+        name: std_env::var(env::WEBAUTHN_RP_NAME_ENV_VAR).unwrap_or(DEFAULT_WEBAUTHN_RP_NAME.to_owned()),
+        origin: std_env::var(env::WEBAUTHN_ORIGIN_ENV_VAR).unwrap_or(DEFAULT_WEBAUTHN_ORIGIN.to_owned()),
+    }
+}
+
+// Requests allowed per client IP and route, as `capacity/period_seconds`.
+// Routes can get their own limit through a comma separated list of `route=capacity/period_seconds`.
+fn set_rate_limits() -> RateLimits {
+    dotenv().ok();
+    let default = std_env::var(env::RATE_LIMIT_ENV_VAR).unwrap_or(DEFAULT_RATE_LIMIT.to_owned());
+    let default = RateLimit::parse(&default).expect("RATE_LIMIT must be formatted as capacity/period_seconds.");
+
+    let routes = std_env::var(env::RATE_LIMIT_ROUTES_ENV_VAR).unwrap_or(DEFAULT_RATE_LIMIT_ROUTES.to_owned());
+    let routes: HashMap<String, RateLimit> = split_list(&routes)
+        .iter()
+        .map(|route| {
+            let (route, limit) = route.split_once('=')
+                .expect("RATE_LIMIT_ROUTES must be a list of route=capacity/period_seconds.");
+            let limit = RateLimit::parse(limit)
+                .expect("RATE_LIMIT_ROUTES must be a list of route=capacity/period_seconds.");
+            (route.trim().to_owned(), limit)
+        })
+        .collect();
+
+    RateLimits { default, routes }
+}
+
+// Comma separated list of the addresses and CIDR blocks of the reverse proxies allowed to pass on
+// the client address. Requests from anywhere else are attributed to the address they came from.
+fn set_trusted_proxies() -> Vec<IpRange> {
+    dotenv().ok();
+    let proxies = std_env::var(env::TRUSTED_PROXIES_ENV_VAR).unwrap_or(DEFAULT_TRUSTED_PROXIES.to_owned());
+    split_list(&proxies)
+        .iter()
+        .map(|proxy| IpRange::parse(proxy).expect("TRUSTED_PROXIES must be a list of IP addresses or CIDR blocks."))
+        .collect()
+}
+
+// Emails are only sent over SMTP once a host is configured, until then they are just logged
+fn set_smtp_config() -> Option<SmtpConfig> {
+    dotenv().ok();
+    let host = std_env::var(env::SMTP_HOST_ENV_VAR).ok().filter(|host| !host.is_empty())?;
+
+    let tls = std_env::var(env::SMTP_TLS_ENV_VAR).unwrap_or(DEFAULT_SMTP_TLS.to_owned());
+    let tls = SmtpTls::parse(&tls).expect("SMTP_TLS must be one of none, starttls or tls.");
+
+    let port = std_env::var(env::SMTP_PORT_ENV_VAR)
+        .map(|port| port.parse().expect("SMTP_PORT must be a valid port."))
+        .unwrap_or(tls.default_port());
+
+    let timeout = std_env::var(env::SMTP_TIMEOUT_SECONDS_ENV_VAR)
+        .map(|seconds| seconds.parse().expect("SMTP_TIMEOUT_SECONDS must be a positive number."))
+        .unwrap_or(DEFAULT_SMTP_TIMEOUT_SECONDS);
+
+    Some(SmtpConfig {
+        host,
+        port,
+        tls,
+        username: std_env::var(env::SMTP_USERNAME_ENV_VAR).ok().filter(|username| !username.is_empty()),
+        password: std_env::var(env::SMTP_PASSWORD_ENV_VAR).ok().filter(|password| !password.is_empty()),
+        from: std_env::var(env::EMAIL_FROM_ENV_VAR).unwrap_or(DEFAULT_EMAIL_FROM.to_owned()),
+        timeout: Duration::from_secs(timeout),
+    })
+}
+
+// Takes precedence over SMTP once an API token is configured
+fn set_email_api_config() -> Option<HttpEmailConfig> {
+    dotenv().ok();
+    let token = std_env::var(env::EMAIL_API_TOKEN_ENV_VAR).ok().filter(|token| !token.is_empty())?;
+
+    let timeout = std_env::var(env::EMAIL_API_TIMEOUT_SECONDS_ENV_VAR)
+        .map(|seconds| seconds.parse().expect("EMAIL_API_TIMEOUT_SECONDS must be a positive number."))
+        .unwrap_or(DEFAULT_EMAIL_API_TIMEOUT_SECONDS);
+
+    let max_retries = std_env::var(env::EMAIL_API_MAX_RETRIES_ENV_VAR)
+        .map(|retries| retries.parse().expect("EMAIL_API_MAX_RETRIES must be a non-negative number."))
+        .unwrap_or(DEFAULT_EMAIL_API_MAX_RETRIES);
+
+    Some(HttpEmailConfig {
+        base_url: std_env::var(env::EMAIL_API_BASE_URL_ENV_VAR).unwrap_or(DEFAULT_EMAIL_API_BASE_URL.to_owned()),
+        token,
+        from: std_env::var(env::EMAIL_FROM_ENV_VAR).unwrap_or(DEFAULT_EMAIL_FROM.to_owned()),
+        timeout: Duration::from_secs(timeout),
+        max_retries,
+        retry_backoff: Duration::from_millis(DEFAULT_EMAIL_API_RETRY_BACKOFF_MILLIS),
+    })
+}
+
+// Directory whose templates replace the ones compiled into the binary
+fn set_email_template_dir() -> Option<PathBuf> {
+    dotenv().ok();
+    std_env::var(env::EMAIL_TEMPLATE_DIR_ENV_VAR).ok().filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
 fn set_database_url() -> String {
     dotenv().ok(); // Load environment variables
     let secret = std_env::var(env::DATABASE_URL).expect("DATABASE_URL must be set.");
@@ -35,11 +288,71 @@ fn set_redis_host() -> String {
 
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_ALGORITHM_ENV_VAR: &str = "JWT_ALGORITHM";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_KEY_ID_ENV_VAR: &str = "JWT_KEY_ID";
+    pub const JWT_KEYRING_PATH_ENV_VAR: &str = "JWT_KEYRING_PATH";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const JWT_DEFAULT_ROLES_ENV_VAR: &str = "JWT_DEFAULT_ROLES";
+    pub const ADMIN_EMAILS_ENV_VAR: &str = "ADMIN_EMAILS";
+    pub const INTROSPECTION_CLIENT_ID_ENV_VAR: &str = "INTROSPECTION_CLIENT_ID";
+    pub const INTROSPECTION_CLIENT_SECRET_ENV_VAR: &str = "INTROSPECTION_CLIENT_SECRET";
+    pub const PASSWORD_RESET_URL_ENV_VAR: &str = "PASSWORD_RESET_URL";
+    pub const EMAIL_VERIFICATION_URL_ENV_VAR: &str = "EMAIL_VERIFICATION_URL";
+    pub const TOTP_ISSUER_ENV_VAR: &str = "TOTP_ISSUER";
+    pub const TOTP_SKEW_STEPS_ENV_VAR: &str = "TOTP_SKEW_STEPS";
+    pub const TOKEN_STATUS_CHECK_ENV_VAR: &str = "TOKEN_STATUS_CHECK";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const WEBAUTHN_RP_NAME_ENV_VAR: &str = "WEBAUTHN_RP_NAME";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const RATE_LIMIT_ENV_VAR: &str = "RATE_LIMIT";
+    pub const RATE_LIMIT_ROUTES_ENV_VAR: &str = "RATE_LIMIT_ROUTES";
+    pub const TRUSTED_PROXIES_ENV_VAR: &str = "TRUSTED_PROXIES";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const SMTP_TIMEOUT_SECONDS_ENV_VAR: &str = "SMTP_TIMEOUT_SECONDS";
+    pub const EMAIL_FROM_ENV_VAR: &str = "EMAIL_FROM";
+    pub const EMAIL_API_BASE_URL_ENV_VAR: &str = "EMAIL_API_BASE_URL";
+    pub const EMAIL_API_TOKEN_ENV_VAR: &str = "EMAIL_API_TOKEN";
+    pub const EMAIL_API_TIMEOUT_SECONDS_ENV_VAR: &str = "EMAIL_API_TIMEOUT_SECONDS";
+    pub const EMAIL_API_MAX_RETRIES_ENV_VAR: &str = "EMAIL_API_MAX_RETRIES";
+    pub const EMAIL_TEMPLATE_DIR_ENV_VAR: &str = "EMAIL_TEMPLATE_DIR";
     pub const DATABASE_URL: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const REAL_IP_HEADER: &str = "x-real-ip";
+pub const DEFAULT_JWT_ALGORITHM: &str = "HS256";
+pub const DEFAULT_JWT_KEY_ID: &str = "default";
+pub const DEFAULT_JWT_ISSUER: &str = "auth-service";
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+pub const DEFAULT_JWT_ROLES: &str = "user";
+pub const DEFAULT_INTROSPECTION_CLIENT_ID: &str = "app-service";
+pub const DEFAULT_PASSWORD_RESET_URL: &str = "http://localhost:3000/password-reset";
+pub const DEFAULT_EMAIL_VERIFICATION_URL: &str = "http://localhost:3000/verify-email";
+pub const DEFAULT_TOTP_ISSUER: &str = "auth-service";
+pub const DEFAULT_TOTP_SKEW_STEPS: i64 = 1;
+pub const DEFAULT_TOKEN_STATUS_CHECK: bool = true;
+pub const DEFAULT_WEBAUTHN_RP_ID: &str = "localhost";
+pub const DEFAULT_WEBAUTHN_RP_NAME: &str = "auth-service";
+pub const DEFAULT_WEBAUTHN_ORIGIN: &str = "http://localhost:8000";
+pub const DEFAULT_RATE_LIMIT: &str = "120/60";
+pub const DEFAULT_RATE_LIMIT_ROUTES: &str = "/signup=30/60,/login=30/60,/verify-2fa=30/60";
+// Only a proxy running on the same host is trusted by default
+pub const DEFAULT_TRUSTED_PROXIES: &str = "127.0.0.1,::1";
+pub const DEFAULT_SMTP_TLS: &str = "starttls";
+pub const DEFAULT_SMTP_TIMEOUT_SECONDS: u64 = 10;
+pub const DEFAULT_EMAIL_FROM: &str = "auth-service <no-reply@localhost>";
+pub const DEFAULT_EMAIL_API_BASE_URL: &str = "https://api.postmarkapp.com";
+pub const DEFAULT_EMAIL_API_TIMEOUT_SECONDS: u64 = 10;
+pub const DEFAULT_EMAIL_API_MAX_RETRIES: u32 = 3;
+pub const DEFAULT_EMAIL_API_RETRY_BACKOFF_MILLIS: u64 = 500;
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1"; // New!
 
 pub mod prod {
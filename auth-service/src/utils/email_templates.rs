@@ -0,0 +1,340 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
+
+use askama::Template;
+use chrono::Utc;
+
+use crate::AppState;
+use crate::domain::{AuthAPIError, Email, EmailMessage, OutboxEmail};
+use super::constants::EMAIL_TEMPLATE_DIR;
+
+// Emails are rendered from the templates in `templates/emails`, which are compiled into the binary.
+// Any of them can be replaced at runtime by a file named `<NAME>.html`, `<NAME>.txt` or `<NAME>.subject`
+// in EMAIL_TEMPLATE_DIR, using the same `{{ name }}` placeholders as the template it replaces.
+pub trait EmailTemplate {
+    const NAME: &'static str;
+
+    fn subject(&self) -> String;
+    fn html(&self) -> askama::Result<String>;
+    fn text(&self) -> askama::Result<String>;
+    // Values the placeholders of replacement templates are filled with
+    fn variables(&self) -> Vec<(&'static str, String)>;
+}
+
+pub fn render_email<T: EmailTemplate>(template: &T) -> askama::Result<EmailMessage> {
+    render_email_from(template, EMAIL_TEMPLATE_DIR.as_deref())
+}
+
+// Renders the email and puts it on the outbox, it's delivered in the background
+pub(crate) async fn queue_email<T: EmailTemplate>(
+    state: &AppState,
+    recipient: &Email,
+    template: &T,
+) -> Result<(), AuthAPIError> {
+    let message = render_email(template).map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state.email_outbox
+        .write()
+        .await
+        .enqueue(OutboxEmail::new(recipient, message, Utc::now().timestamp()))
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+fn render_email_from<T: EmailTemplate>(template: &T, dir: Option<&Path>) -> askama::Result<EmailMessage> {
+    let variables = template.variables();
+
+    // Replacements are read on every email, so they can be edited without a restart
+    let replacement = |extension: &str| {
+        dir.and_then(|dir| fs::read_to_string(dir.join(format!("{}.{}", T::NAME, extension))).ok())
+    };
+
+    Ok(EmailMessage {
+        subject: match replacement("subject") {
+            Some(subject) => fill_placeholders(subject.trim(), &variables, false),
+            None => template.subject(),
+        },
+        html: match replacement("html") {
+            Some(html) => fill_placeholders(&html, &variables, true),
+            None => template.html()?,
+        },
+        text: match replacement("txt") {
+            Some(text) => fill_placeholders(&text, &variables, false),
+            None => template.text()?,
+        },
+    })
+}
+
+// Unknown placeholders are left as they are, so a typo shows up in the email instead of an empty gap
+fn fill_placeholders(source: &str, variables: &[(&str, String)], escape_html: bool) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else { break };
+        let placeholder = &rest[start..start + length + 2];
+        let name = placeholder[2..placeholder.len() - 2].trim();
+
+        output.push_str(&rest[..start]);
+        match variables.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) if escape_html => output.push_str(&escape_html_text(value)),
+            Some((_, value)) => output.push_str(value),
+            None => output.push_str(placeholder),
+        }
+        rest = &rest[start + length + 2..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn escape_html_text(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.html")]
+pub struct TwoFACodeEmail<'a> {
+    pub code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.txt")]
+struct TwoFACodeEmailText<'a>(&'a TwoFACodeEmail<'a>);
+
+impl<'a> Deref for TwoFACodeEmailText<'a> {
+    type Target = TwoFACodeEmail<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl EmailTemplate for TwoFACodeEmail<'_> {
+    const NAME: &'static str = "two_fa_code";
+
+    fn subject(&self) -> String {
+        "Your 2FA Code".to_owned()
+    }
+
+    fn html(&self) -> askama::Result<String> {
+        self.render()
+    }
+
+    fn text(&self) -> askama::Result<String> {
+        TwoFACodeEmailText(self).render()
+    }
+
+    fn variables(&self) -> Vec<(&'static str, String)> {
+        vec![("code", self.code.to_owned())]
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_verification.html")]
+pub struct EmailVerificationEmail<'a> {
+    pub url: &'a str,
+    pub expires_in_hours: i64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_verification.txt")]
+struct EmailVerificationEmailText<'a>(&'a EmailVerificationEmail<'a>);
+
+impl<'a> Deref for EmailVerificationEmailText<'a> {
+    type Target = EmailVerificationEmail<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl EmailTemplate for EmailVerificationEmail<'_> {
+    const NAME: &'static str = "email_verification";
+
+    fn subject(&self) -> String {
+        "Verify your email address".to_owned()
+    }
+
+    fn html(&self) -> askama::Result<String> {
+        self.render()
+    }
+
+    fn text(&self) -> askama::Result<String> {
+        EmailVerificationEmailText(self).render()
+    }
+
+    fn variables(&self) -> Vec<(&'static str, String)> {
+        vec![("url", self.url.to_owned()), ("expires_in_hours", self.expires_in_hours.to_string())]
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.html")]
+pub struct PasswordResetEmail<'a> {
+    pub url: &'a str,
+    pub expires_in_minutes: i64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.txt")]
+struct PasswordResetEmailText<'a>(&'a PasswordResetEmail<'a>);
+
+impl<'a> Deref for PasswordResetEmailText<'a> {
+    type Target = PasswordResetEmail<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl EmailTemplate for PasswordResetEmail<'_> {
+    const NAME: &'static str = "password_reset";
+
+    fn subject(&self) -> String {
+        "Reset your password".to_owned()
+    }
+
+    fn html(&self) -> askama::Result<String> {
+        self.render()
+    }
+
+    fn text(&self) -> askama::Result<String> {
+        PasswordResetEmailText(self).render()
+    }
+
+    fn variables(&self) -> Vec<(&'static str, String)> {
+        vec![("url", self.url.to_owned()), ("expires_in_minutes", self.expires_in_minutes.to_string())]
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/new_login.html")]
+pub struct NewLoginEmail<'a> {
+    pub time: &'a str,
+    pub ip: &'a str,
+    pub user_agent: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/new_login.txt")]
+struct NewLoginEmailText<'a>(&'a NewLoginEmail<'a>);
+
+impl<'a> Deref for NewLoginEmailText<'a> {
+    type Target = NewLoginEmail<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl EmailTemplate for NewLoginEmail<'_> {
+    const NAME: &'static str = "new_login";
+
+    fn subject(&self) -> String {
+        "New login to your account".to_owned()
+    }
+
+    fn html(&self) -> askama::Result<String> {
+        self.render()
+    }
+
+    fn text(&self) -> askama::Result<String> {
+        NewLoginEmailText(self).render()
+    }
+
+    fn variables(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("time", self.time.to_owned()),
+            ("ip", self.ip.to_owned()),
+            ("user_agent", self.user_agent.to_owned()),
+        ]
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/account_deleted.html")]
+pub struct AccountDeletedEmail<'a> {
+    pub time: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/account_deleted.txt")]
+struct AccountDeletedEmailText<'a>(&'a AccountDeletedEmail<'a>);
+
+impl<'a> Deref for AccountDeletedEmailText<'a> {
+    type Target = AccountDeletedEmail<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl EmailTemplate for AccountDeletedEmail<'_> {
+    const NAME: &'static str = "account_deleted";
+
+    fn subject(&self) -> String {
+        "Your account has been deleted".to_owned()
+    }
+
+    fn html(&self) -> askama::Result<String> {
+        self.render()
+    }
+
+    fn text(&self) -> askama::Result<String> {
+        AccountDeletedEmailText(self).render()
+    }
+
+    fn variables(&self) -> Vec<(&'static str, String)> {
+        vec![("time", self.time.to_owned())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_compiled_templates() {
+        let message = render_email_from(&TwoFACodeEmail { code: "123456" }, None).unwrap();
+
+        assert_eq!(message.subject, "Your 2FA Code");
+        assert!(message.html.contains("<html"));
+        assert!(message.html.contains("123456"));
+        assert!(message.text.contains("123456"));
+        assert!(!message.text.contains('<'));
+    }
+
+    #[test]
+    fn test_escape_html_in_compiled_templates() {
+        let email = NewLoginEmail { time: "now", ip: "127.0.0.1", user_agent: "<script>" };
+        let message = render_email_from(&email, None).unwrap();
+
+        assert!(message.html.contains("&lt;script&gt;"));
+        assert!(message.text.contains("<script>"));
+    }
+
+    #[test]
+    fn test_render_replacement_templates() {
+        let dir = std::env::temp_dir().join(format!("email-templates-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("password_reset.subject"), "Reset it within {{expires_in_minutes}} minutes\n").unwrap();
+        fs::write(dir.join("password_reset.html"), "<a href=\"{{ url }}\">Reset</a> {{ unknown }}").unwrap();
+
+        let email = PasswordResetEmail { url: "https://example.com/?a=1&b=2", expires_in_minutes: 30 };
+        let message = render_email_from(&email, Some(&dir)).unwrap();
+
+        assert_eq!(message.subject, "Reset it within 30 minutes");
+        assert_eq!(message.html, "<a href=\"https://example.com/?a=1&amp;b=2\">Reset</a> {{ unknown }}");
+        // Formats without a replacement still come from the compiled template
+        assert!(message.text.contains("https://example.com/?a=1&b=2"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
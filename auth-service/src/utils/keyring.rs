@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::jwk::JwkSet;
+use serde::Deserialize;
+
+use super::auth::TOKEN_TTL_SECONDS;
+use super::signing_key::{SigningKey, SigningKeyError};
+
+// Signing keys identified by the `kid` JWT header. Tokens are signed with the active key only,
+// while retired keys keep verifying the tokens they signed until those have expired.
+pub struct Keyring {
+    active_kid: String,
+    keys: HashMap<String, KeyringEntry>,
+}
+
+struct KeyringEntry {
+    key: SigningKey,
+    retired_at: Option<i64>,
+}
+
+impl KeyringEntry {
+    fn is_accepted(&self, now: i64) -> bool {
+        match self.retired_at {
+            Some(retired_at) => now <= retired_at + TOKEN_TTL_SECONDS,
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeyringError {
+    UnknownActiveKey,
+    DuplicateKeyId,
+    MissingKeyMaterial,
+    UnreadableKey,
+    InvalidKey(SigningKeyError),
+}
+
+impl Keyring {
+    pub fn new(active_kid: String, active_key: SigningKey) -> Self {
+        let mut keys = HashMap::new();
+        keys.insert(active_kid.clone(), KeyringEntry { key: active_key, retired_at: None });
+
+        Keyring { active_kid, keys }
+    }
+
+    // Keep accepting tokens signed with a key that was replaced at `retired_at` (Unix timestamp)
+    pub fn add_retired_key(&mut self, kid: String, key: SigningKey, retired_at: i64)
+        -> Result<(), KeyringError> {
+        if self.keys.contains_key(&kid) {
+            return Err(KeyringError::DuplicateKeyId);
+        }
+
+        self.keys.insert(kid, KeyringEntry { key, retired_at: Some(retired_at) });
+        Ok(())
+    }
+
+    pub fn from_config(config: KeyringConfig) -> Result<Self, KeyringError> {
+        let mut active = None;
+        let mut retired = Vec::new();
+
+        for key_config in config.keys {
+            let key = key_config.signing_key()?;
+
+            if key_config.kid == config.active {
+                if active.is_some() {
+                    return Err(KeyringError::DuplicateKeyId);
+                }
+                active = Some(key);
+            } else {
+                // A key without a retirement date has to be kept until it's removed from the config
+                let retired_at = key_config.retired_at.unwrap_or(i64::MAX - TOKEN_TTL_SECONDS);
+                retired.push((key_config.kid, key, retired_at));
+            }
+        }
+
+        let active = active.ok_or(KeyringError::UnknownActiveKey)?;
+        let mut keyring = Keyring::new(config.active, active);
+
+        for (kid, key, retired_at) in retired {
+            keyring.add_retired_key(kid, key, retired_at)?;
+        }
+
+        Ok(keyring)
+    }
+
+    // Key id and key used to sign new tokens
+    pub fn active(&self) -> (&str, &SigningKey) {
+        (&self.active_kid, &self.keys[&self.active_kid].key)
+    }
+
+    // Key verifying a token with the given `kid` header. Tokens issued before key ids were
+    // introduced carry no `kid` and are checked against the active key.
+    pub fn verification_key(&self, kid: Option<&str>) -> Option<&SigningKey> {
+        let kid = kid.unwrap_or(&self.active_kid);
+
+        self.keys
+            .get(kid)
+            .filter(|entry| entry.is_accepted(Utc::now().timestamp()))
+            .map(|entry| &entry.key)
+    }
+
+    // Public keys of every key still accepted for verification, so downstream services
+    // can verify tokens signed before a rotation
+    pub fn jwk_set(&self) -> JwkSet {
+        let now = Utc::now().timestamp();
+
+        let mut keys: Vec<_> = self.keys
+            .iter()
+            .filter(|(_, entry)| entry.is_accepted(now))
+            .filter_map(|(kid, entry)| {
+                entry.key.jwk().map(|jwk| {
+                    let mut jwk = jwk.clone();
+                    jwk.common.key_id = Some(kid.clone());
+                    jwk
+                })
+            })
+            .collect();
+
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+
+        JwkSet { keys }
+    }
+}
+
+// Keyring file referenced by JWT_KEYRING_PATH
+#[derive(Debug, Deserialize)]
+pub struct KeyringConfig {
+    pub active: String,
+    pub keys: Vec<KeyConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeyConfig {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    // Shared secret of an HS256 key
+    pub secret: Option<String>,
+    // PEM file with the private key of an RS256 or EdDSA key
+    #[serde(rename = "privateKeyPath")]
+    pub private_key_path: Option<String>,
+    #[serde(rename = "retiredAt")]
+    pub retired_at: Option<i64>,
+}
+
+impl KeyConfig {
+    fn signing_key(&self) -> Result<SigningKey, KeyringError> {
+        if self.algorithm == Algorithm::HS256 {
+            let secret = self.secret.as_ref().ok_or(KeyringError::MissingKeyMaterial)?;
+            return Ok(SigningKey::hs256(secret.as_bytes()));
+        }
+
+        let path = self.private_key_path.as_ref().ok_or(KeyringError::MissingKeyMaterial)?;
+        let pem = std::fs::read(path).map_err(|_| KeyringError::UnreadableKey)?;
+
+        SigningKey::from_pem(self.algorithm, &pem).map_err(KeyringError::InvalidKey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ED25519_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_ed25519_private_key.pem");
+    const RSA_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_rsa_private_key.pem");
+
+    fn keyring() -> Keyring {
+        let mut keyring = Keyring::new(
+            "current".to_owned(),
+            SigningKey::from_pem(Algorithm::EdDSA, ED25519_PRIVATE_KEY).unwrap(),
+        );
+        keyring
+            .add_retired_key(
+                "previous".to_owned(),
+                SigningKey::from_pem(Algorithm::RS256, RSA_PRIVATE_KEY).unwrap(),
+                Utc::now().timestamp(),
+            )
+            .unwrap();
+        keyring
+            .add_retired_key(
+                "expired".to_owned(),
+                SigningKey::hs256(b"secret"),
+                Utc::now().timestamp() - TOKEN_TTL_SECONDS - 1,
+            )
+            .unwrap();
+        keyring
+    }
+
+    #[test]
+    fn test_active_key() {
+        let keyring = keyring();
+        let (kid, key) = keyring.active();
+        assert_eq!(kid, "current");
+        assert_eq!(key.algorithm(), Algorithm::EdDSA);
+    }
+
+    #[test]
+    fn test_verification_key() {
+        let keyring = keyring();
+        assert_eq!(keyring.verification_key(Some("current")).unwrap().algorithm(), Algorithm::EdDSA);
+        assert_eq!(keyring.verification_key(Some("previous")).unwrap().algorithm(), Algorithm::RS256);
+        assert_eq!(keyring.verification_key(None).unwrap().algorithm(), Algorithm::EdDSA);
+        assert!(keyring.verification_key(Some("expired")).is_none());
+        assert!(keyring.verification_key(Some("unknown")).is_none());
+    }
+
+    #[test]
+    fn test_jwk_set_contains_accepted_keys() {
+        let kids: Vec<_> = keyring()
+            .jwk_set()
+            .keys
+            .into_iter()
+            .map(|jwk| jwk.common.key_id.unwrap())
+            .collect();
+        assert_eq!(kids, vec!["current", "previous"]);
+    }
+
+    #[test]
+    fn test_duplicate_key_id() {
+        let mut keyring = keyring();
+        assert_eq!(
+            keyring.add_retired_key("current".to_owned(), SigningKey::hs256(b"secret"), 0),
+            Err(KeyringError::DuplicateKeyId)
+        );
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config: KeyringConfig = serde_json::from_value(serde_json::json!({
+            "active": "new",
+            "keys": [
+                { "kid": "old", "algorithm": "HS256", "secret": "old-secret", "retiredAt": Utc::now().timestamp() },
+                { "kid": "new", "algorithm": "HS256", "secret": "new-secret" }
+            ]
+        }))
+            .unwrap();
+
+        let keyring = Keyring::from_config(config).unwrap();
+        assert_eq!(keyring.active().0, "new");
+        assert!(keyring.verification_key(Some("old")).is_some());
+    }
+
+    #[test]
+    fn test_from_config_without_active_key() {
+        let config: KeyringConfig = serde_json::from_value(serde_json::json!({
+            "active": "missing",
+            "keys": [{ "kid": "new", "algorithm": "HS256", "secret": "new-secret" }]
+        }))
+            .unwrap();
+
+        assert_eq!(Keyring::from_config(config).err(), Some(KeyringError::UnknownActiveKey));
+
+        let config: KeyringConfig = serde_json::from_value(serde_json::json!({
+            "active": "new",
+            "keys": [{ "kid": "new", "algorithm": "HS256" }]
+        }))
+            .unwrap();
+
+        assert_eq!(Keyring::from_config(config).err(), Some(KeyringError::MissingKeyMaterial));
+    }
+}
@@ -1,2 +1,13 @@
 pub mod constants;
+pub mod audit;
 pub mod auth;
+pub mod client_credentials;
+pub mod client_info;
+pub mod constant_time;
+pub mod email_templates;
+pub mod keyring;
+pub mod rate_limit;
+pub mod roles;
+pub mod signing_key;
+pub mod totp;
+pub mod webauthn;
@@ -0,0 +1,71 @@
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::HeaderValue;
+use axum::http::header::RETRY_AFTER;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::AppState;
+use crate::domain::{AuthAPIError, RateLimitStatus};
+
+use super::client_info::ClientInfo;
+use super::constants::RATE_LIMITS;
+
+// Headers from the IETF RateLimit header fields draft
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "ratelimit-limit";
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "ratelimit-remaining";
+pub const RATE_LIMIT_RESET_HEADER: &str = "ratelimit-reset";
+
+// Called by other services on behalf of all their users, so a shared limit would throttle all of them.
+// They don't deal with passwords or codes, so there is nothing to guess.
+pub const RATE_LIMIT_EXEMPT_ROUTES: [&str; 2] = ["/verify-token", "/introspect"];
+
+// Token bucket per client IP and route, requests over the limit are answered with 429
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Response {
+    // The route pattern rather than the path, so `/sessions/:id` shares one bucket for all ids
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| request.uri().path().to_owned());
+
+    if RATE_LIMIT_EXEMPT_ROUTES.contains(&route.as_str()) {
+        return next.run(request).await;
+    }
+
+    let limit = RATE_LIMITS.for_route(&route);
+    let key = format!("{}:{}", client.ip.as_deref().unwrap_or("unknown"), route);
+
+    let result = state.rate_limit_store.write().await.take_token(&key, limit).await;
+
+    // A broken limiter shouldn't take the whole service down with it
+    let status = match result {
+        Ok(status) => status,
+        Err(_) => return next.run(request).await,
+    };
+
+    let mut response = match status.allowed {
+        true => next.run(request).await,
+        false => AuthAPIError::TooManyRequests.into_response(),
+    };
+
+    add_headers(&mut response, limit.capacity, &status);
+
+    response
+}
+
+fn add_headers(response: &mut Response, capacity: u32, status: &RateLimitStatus) {
+    let headers = response.headers_mut();
+
+    headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from(capacity));
+    headers.insert(RATE_LIMIT_REMAINING_HEADER, HeaderValue::from(status.remaining));
+    headers.insert(RATE_LIMIT_RESET_HEADER, HeaderValue::from(status.reset_seconds));
+
+    if !status.allowed {
+        headers.insert(RETRY_AFTER, HeaderValue::from(status.retry_after_seconds));
+    }
+}
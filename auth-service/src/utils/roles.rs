@@ -0,0 +1,189 @@
+use std::marker::PhantomData;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+use crate::AppState;
+use crate::app_state::UserStoreType;
+use crate::domain::{AuthAPIError, Email, User, UserStoreError, ADMIN_ROLE};
+use super::auth::{user_roles, AuthenticatedUser};
+
+// A role a route can require, see `RequireRole`
+pub trait Role {
+    const NAME: &'static str;
+}
+
+pub struct Admin;
+
+impl Role for Admin {
+    const NAME: &'static str = ADMIN_ROLE;
+}
+
+// Extracts the user the `jwt` cookie was issued to, rejecting the request unless they hold the role `R`.
+// The roles are read from the user store rather than the token, so taking a role away applies at once.
+//
+//     async fn handler(RequireRole(user, _): RequireRole<Admin>) { ... }
+pub struct RequireRole<R: Role>(pub User, pub PhantomData<R>);
+
+#[async_trait]
+impl<R: Role> FromRequestParts<AppState> for RequireRole<R> {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let AuthenticatedUser { user, .. } = AuthenticatedUser::from_request_parts(parts, state).await?;
+
+        match user_roles(&user).iter().any(|role| role == R::NAME) {
+            true => Ok(RequireRole(user, PhantomData)),
+            false => Err(AuthAPIError::Forbidden),
+        }
+    }
+}
+
+// Grants the admin role to each of the configured users that has signed up, see `ADMIN_EMAILS`
+pub async fn seed_admins(user_store: &UserStoreType, admin_emails: &[String]) -> Result<(), UserStoreError> {
+    for email in admin_emails {
+        // ADMIN_EMAILS is matched case-insensitively but the user store isn't, so entries are looked up in lowercase
+        match Email::parse(&email.to_lowercase()) {
+            Ok(email) => grant_configured_admin_role(user_store, &email, admin_emails).await?,
+            Err(_) => continue,
+        }
+    }
+
+    Ok(())
+}
+
+// Grants the admin role if the user is one of the configured admins. Users who haven't signed up yet
+// get it on signup, they can only log in once they've verified they own the email address.
+pub async fn grant_configured_admin_role(
+    user_store: &UserStoreType,
+    email: &Email,
+    admin_emails: &[String],
+) -> Result<(), UserStoreError> {
+    if !admin_emails.iter().any(|admin_email| admin_email.eq_ignore_ascii_case(email.as_ref())) {
+        return Ok(());
+    }
+
+    let mut user_store = user_store.write().await;
+
+    let mut roles = match user_store.get_user(email).await {
+        Ok(user) => user.roles,
+        Err(UserStoreError::UserNotFound) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if roles.iter().any(|role| role == ADMIN_ROLE) {
+        return Ok(());
+    }
+
+    roles.push(ADMIN_ROLE.to_owned());
+
+    user_store.set_roles(email, roles).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::{header::COOKIE, Request};
+    use tokio::sync::RwLock;
+
+    use crate::domain::{AccountStatus, Password, UserStore};
+    use crate::services::data_stores::*;
+    use crate::utils::auth;
+    use crate::utils::client_info::ClientInfo;
+    use crate::utils::constants::JWT_COOKIE_NAME;
+
+    use super::*;
+
+    fn test_state(user_store: HashmapUserStore) -> AppState {
+        AppState::new(
+            Arc::new(RwLock::new(user_store)),
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default())),
+            Arc::new(RwLock::new(HashmapSessionStore::default())),
+            Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default())),
+            Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default())),
+            Arc::new(RwLock::new(HashmapCredentialStore::default())),
+            Arc::new(RwLock::new(HashmapWebAuthnChallengeStore::default())),
+            Arc::new(RwLock::new(HashmapLoginAttemptStore::default())),
+            Arc::new(RwLock::new(HashmapRateLimitStore::default())),
+            Arc::new(RwLock::new(HashmapEmailOutboxStore::default())),
+            Arc::new(RwLock::new(HashmapAuditLogStore::default())),
+        )
+    }
+
+    async fn request_parts(state: &AppState, user: &User) -> Parts {
+        let cookie = auth::generate_auth_cookie(user, &ClientInfo::default(), None, state.session_store.clone())
+            .await
+            .unwrap();
+
+        let (parts, _) = Request::builder()
+            .header(COOKIE, format!("{}={}", JWT_COOKIE_NAME, cookie.value()))
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        parts
+    }
+
+    async fn extract_admin(user_roles: Vec<String>) -> Result<RequireRole<Admin>, AuthAPIError> {
+        let mut user = User::new(Email::parse("user@example.com").unwrap(), Password::parse("password123").unwrap(), false);
+        user.roles = user_roles;
+        user.status = AccountStatus::Active;
+
+        let mut user_store = HashmapUserStore::default();
+        user_store.add_user(user.clone()).await.unwrap();
+
+        let state = test_state(user_store);
+        let mut parts = request_parts(&state, &user).await;
+
+        RequireRole::<Admin>::from_request_parts(&mut parts, &state).await
+    }
+
+    #[tokio::test]
+    async fn test_require_role_with_role() {
+        let RequireRole(user, _) = extract_admin(vec![ADMIN_ROLE.to_owned()]).await.ok().unwrap();
+
+        assert_eq!(user.email.as_ref(), "user@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_require_role_without_role() {
+        assert!(matches!(extract_admin(vec![]).await, Err(AuthAPIError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_require_role_without_token() {
+        let state = test_state(HashmapUserStore::default());
+        let (mut parts, _) = Request::builder().body(()).unwrap().into_parts();
+
+        let result = RequireRole::<Admin>::from_request_parts(&mut parts, &state).await;
+
+        assert!(matches!(result, Err(AuthAPIError::MissingToken)));
+    }
+
+    #[tokio::test]
+    async fn test_seed_admins() {
+        let mut user_store = HashmapUserStore::default();
+
+        for email in ["admin@example.com", "user@example.com"] {
+            let user = User::new(Email::parse(email).unwrap(), Password::parse("password123").unwrap(), false);
+            user_store.add_user(user).await.unwrap();
+        }
+
+        let user_store: UserStoreType = Arc::new(RwLock::new(user_store));
+        let admin_emails = vec!["Admin@example.com".to_owned(), "missing@example.com".to_owned()];
+
+        // Seeding twice doesn't grant the role twice
+        seed_admins(&user_store, &admin_emails).await.unwrap();
+        seed_admins(&user_store, &admin_emails).await.unwrap();
+
+        let admin = user_store.read().await.get_user(&Email::parse("admin@example.com").unwrap()).await.unwrap();
+        assert_eq!(admin.roles, vec![ADMIN_ROLE.to_owned()]);
+
+        let user = user_store.read().await.get_user(&Email::parse("user@example.com").unwrap()).await.unwrap();
+        assert!(user.roles.is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+
+// Key used to sign JWT auth tokens, together with the key needed to verify them
+pub struct SigningKey {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    // Public part of an asymmetric key, published so other services can verify tokens locally
+    jwk: Option<Jwk>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SigningKeyError {
+    UnsupportedAlgorithm,
+    InvalidKey,
+}
+
+impl SigningKey {
+    // Shared secret key. Tokens can only be verified by services knowing the secret.
+    pub fn hs256(secret: &[u8]) -> Self {
+        SigningKey {
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    // Private key for RS256 (PKCS#1 or PKCS#8) or EdDSA (PKCS#8), read from a PEM file
+    pub fn from_pem(algorithm: Algorithm, pem: &[u8]) -> Result<Self, SigningKeyError> {
+        let der = pem::parse(pem).map_err(|_| SigningKeyError::InvalidKey)?;
+
+        match algorithm {
+            Algorithm::RS256 => {
+                let key_pair = match der.tag() {
+                    "RSA PRIVATE KEY" => RsaKeyPair::from_der(der.contents()),
+                    "PRIVATE KEY" => RsaKeyPair::from_pkcs8(der.contents()),
+                    _ => return Err(SigningKeyError::InvalidKey),
+                }
+                    .map_err(|_| SigningKeyError::InvalidKey)?;
+
+                let public_key = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+                let n = URL_SAFE_NO_PAD.encode(public_key.n);
+                let e = URL_SAFE_NO_PAD.encode(public_key.e);
+
+                let decoding_key = DecodingKey::from_rsa_components(&n, &e)
+                    .map_err(|_| SigningKeyError::InvalidKey)?;
+
+                let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n,
+                    e,
+                });
+
+                Ok(SigningKey {
+                    algorithm,
+                    encoding_key: EncodingKey::from_rsa_pem(pem)
+                        .map_err(|_| SigningKeyError::InvalidKey)?,
+                    decoding_key,
+                    jwk: Some(public_jwk(KeyAlgorithm::RS256, parameters)),
+                })
+            }
+            Algorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der.contents())
+                    .map_err(|_| SigningKeyError::InvalidKey)?;
+
+                let x = URL_SAFE_NO_PAD.encode(key_pair.public_key());
+
+                let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: x.clone(),
+                });
+
+                Ok(SigningKey {
+                    algorithm,
+                    encoding_key: EncodingKey::from_ed_pem(pem)
+                        .map_err(|_| SigningKeyError::InvalidKey)?,
+                    decoding_key: DecodingKey::from_ed_components(&x)
+                        .map_err(|_| SigningKeyError::InvalidKey)?,
+                    jwk: Some(public_jwk(KeyAlgorithm::EdDSA, parameters)),
+                })
+            }
+            _ => Err(SigningKeyError::UnsupportedAlgorithm),
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    // Public key in the JWK format. None for HS256, whose secret must never be published.
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+fn public_jwk(algorithm: KeyAlgorithm, parameters: AlgorithmParameters) -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(algorithm),
+            ..CommonParameters::default()
+        },
+        algorithm: parameters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, encode, Header, Validation};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    const RSA_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_rsa_private_key.pem");
+    const ED25519_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_ed25519_private_key.pem");
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims { sub: "test@example.com".to_owned(), exp: 10_000_000_000 }
+    }
+
+    // Tokens have to verify both with the service's own key and with the key published in the JWKS
+    fn assert_round_trip(key: &SigningKey) {
+        let token = encode(&Header::new(key.algorithm()), &claims(), key.encoding_key()).unwrap();
+        let validation = Validation::new(key.algorithm());
+
+        let decoded = decode::<TestClaims>(&token, key.decoding_key(), &validation).unwrap();
+        assert_eq!(decoded.claims, claims());
+
+        let published = DecodingKey::from_jwk(key.jwk().unwrap()).unwrap();
+        let decoded = decode::<TestClaims>(&token, &published, &validation).unwrap();
+        assert_eq!(decoded.claims, claims());
+    }
+
+    #[test]
+    fn test_hs256_key_is_not_published() {
+        let key = SigningKey::hs256(b"secret");
+        assert_eq!(key.algorithm(), Algorithm::HS256);
+        assert!(key.jwk().is_none());
+    }
+
+    #[test]
+    fn test_rs256_key() {
+        let key = SigningKey::from_pem(Algorithm::RS256, RSA_PRIVATE_KEY).unwrap();
+        assert_round_trip(&key);
+
+        let jwk = key.jwk().unwrap();
+        assert_eq!(jwk.common.key_algorithm, Some(KeyAlgorithm::RS256));
+        assert!(matches!(jwk.algorithm, AlgorithmParameters::RSA(_)));
+    }
+
+    #[test]
+    fn test_eddsa_key() {
+        let key = SigningKey::from_pem(Algorithm::EdDSA, ED25519_PRIVATE_KEY).unwrap();
+        assert_round_trip(&key);
+
+        let jwk = key.jwk().unwrap();
+        assert_eq!(jwk.common.key_algorithm, Some(KeyAlgorithm::EdDSA));
+        assert!(matches!(jwk.algorithm, AlgorithmParameters::OctetKeyPair(_)));
+    }
+
+    #[test]
+    fn test_key_not_matching_algorithm() {
+        assert_eq!(
+            SigningKey::from_pem(Algorithm::EdDSA, RSA_PRIVATE_KEY).err(),
+            Some(SigningKeyError::InvalidKey)
+        );
+        assert_eq!(
+            SigningKey::from_pem(Algorithm::HS512, RSA_PRIVATE_KEY).err(),
+            Some(SigningKeyError::UnsupportedAlgorithm)
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use ring::hmac;
+
+use crate::domain::{Email, TwoFACode};
+
+use super::constant_time::constant_time_eq;
+
+// RFC 6238 parameters understood by every authenticator app: HMAC-SHA1, 30 second steps, 6 digits
+const TIME_STEP_SECONDS: i64 = 30;
+const SECRET_LENGTH: usize = 20;
+const DIGITS_MODULO: u32 = 1_000_000;
+
+// Random 160-bit secret, base32 encoded as expected by authenticator apps
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_LENGTH];
+    rand::thread_rng().fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+// URI shown as a QR code, so the secret doesn't have to be typed into the app
+pub fn provisioning_uri(secret: &str, email: &Email, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits=6&period={}",
+        percent_encode(issuer),
+        percent_encode(email.as_ref()),
+        secret,
+        percent_encode(issuer),
+        TIME_STEP_SECONDS,
+    )
+}
+
+// Code an authenticator app shows at `timestamp`. None if the secret isn't valid base32.
+pub fn generate_code(secret: &str, timestamp: i64) -> Option<TwoFACode> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, &key);
+
+    TwoFACode::parse(code_at(&key, timestamp.div_euclid(TIME_STEP_SECONDS) as u64)).ok()
+}
+
+// Accept codes from up to `skew` time steps before or after `timestamp`, so clocks running
+// slightly apart don't lock the user out
+pub fn verify_code(secret: &str, code: &TwoFACode, timestamp: i64, skew: i64) -> bool {
+    let key = match BASE32_NOPAD.decode(secret.as_bytes()) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, &key);
+    let counter = timestamp.div_euclid(TIME_STEP_SECONDS);
+
+    (-skew..=skew)
+        .filter(|step| counter + step >= 0)
+        .any(|step| constant_time_eq(code_at(&key, (counter + step) as u64).as_bytes(), code.as_ref().as_bytes()))
+}
+
+// RFC 4226 HOTP value with dynamic truncation
+fn code_at(key: &hmac::Key, counter: u64) -> String {
+    let digest = hmac::sign(key, &counter.to_be_bytes());
+    let digest = digest.as_ref();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]])
+        & 0x7fff_ffff;
+
+    format!("{:06}", binary % DIGITS_MODULO)
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Secret of the RFC 6238 SHA1 test vectors
+    fn rfc_secret() -> String {
+        BASE32_NOPAD.encode(b"12345678901234567890")
+    }
+
+    fn code(code: &str) -> TwoFACode {
+        TwoFACode::parse(code.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn test_rfc_6238_vectors() {
+        // The RFC lists 8-digit values, authenticator apps show their last 6 digits
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ];
+
+        for (timestamp, expected) in vectors {
+            assert_eq!(generate_code(&rfc_secret(), timestamp), Some(code(expected)));
+            assert!(verify_code(&rfc_secret(), &code(expected), timestamp, 0), "timestamp {}", timestamp);
+        }
+    }
+
+    #[test]
+    fn test_skew_window() {
+        // 287082 is the code of the step ending at 59
+        assert!(!verify_code(&rfc_secret(), &code("287082"), 60, 0));
+        assert!(verify_code(&rfc_secret(), &code("287082"), 60, 1));
+        assert!(!verify_code(&rfc_secret(), &code("287082"), 90, 1));
+        assert!(verify_code(&rfc_secret(), &code("287082"), 0, 1));
+    }
+
+    #[test]
+    fn test_generated_secret() {
+        let secret = generate_secret();
+        assert_eq!(BASE32_NOPAD.decode(secret.as_bytes()).unwrap().len(), SECRET_LENGTH);
+        assert_ne!(secret, generate_secret());
+        assert!(!verify_code("not base32!", &code("123456"), 59, 1));
+        assert_eq!(generate_code("not base32!", 59), None);
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let email = Email::parse("user@example.com").unwrap();
+        assert_eq!(
+            provisioning_uri("JBSWY3DPEHPK3PXP", &email, "Auth Service"),
+            "otpauth://totp/Auth%20Service:user%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Auth%20Service&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -0,0 +1,655 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value;
+use rand::RngCore;
+use ring::digest::{digest, SHA256};
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, ED25519, RSA_PKCS1_2048_8192_SHA256,
+};
+use serde::Deserialize;
+
+use crate::domain::Email;
+
+// COSE algorithm identifiers offered to authenticators, most preferred first
+pub const COSE_ALGORITHM_ES256: i64 = -7;
+pub const COSE_ALGORITHM_EDDSA: i64 = -8;
+pub const COSE_ALGORITHM_RS256: i64 = -257;
+pub const SUPPORTED_ALGORITHMS: [i64; 3] = [COSE_ALGORITHM_ES256, COSE_ALGORITHM_EDDSA, COSE_ALGORITHM_RS256];
+
+// COSE key types and curves the supported algorithms use
+const COSE_KEY_TYPE_OKP: i128 = 1;
+const COSE_KEY_TYPE_EC2: i128 = 2;
+const COSE_KEY_TYPE_RSA: i128 = 3;
+const COSE_CURVE_P256: i128 = 1;
+const COSE_CURVE_ED25519: i128 = 6;
+
+const CHALLENGE_LENGTH: usize = 32;
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// The site passkeys are scoped to. Browsers only release a credential to pages on `origin`,
+// whose host has to match `id` or be a subdomain of it.
+#[derive(Clone, Debug)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+    pub origin: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WebAuthnError {
+    InvalidEncoding,
+    InvalidClientData,
+    InvalidAuthenticatorData,
+    UnsupportedAlgorithm,
+    InvalidSignature,
+    // The signature counter went backwards, so the credential may have been cloned
+    ClonedAuthenticator,
+}
+
+pub fn generate_challenge() -> String {
+    let mut challenge = [0u8; CHALLENGE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut challenge);
+    URL_SAFE_NO_PAD.encode(challenge)
+}
+
+// Opaque user id stored by authenticators. The spec asks for it not to contain the email itself.
+pub fn user_handle(email: &Email) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, email.as_ref().as_bytes()))
+}
+
+// clientDataJSON collected by the browser. The raw bytes are kept, as assertions sign their hash.
+pub struct ClientData {
+    raw: Vec<u8>,
+    fields: ClientDataFields,
+}
+
+#[derive(Deserialize)]
+struct ClientDataFields {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+impl ClientData {
+    pub fn parse(encoded: &str) -> Result<Self, WebAuthnError> {
+        let raw = decode(encoded)?;
+        let fields = serde_json::from_slice(&raw).map_err(|_| WebAuthnError::InvalidEncoding)?;
+
+        Ok(ClientData { raw, fields })
+    }
+
+    pub fn challenge(&self) -> &str {
+        &self.fields.challenge
+    }
+
+    fn verify(&self, rp: &RelyingParty, ceremony_type: &str) -> Result<(), WebAuthnError> {
+        if self.fields.ceremony_type != ceremony_type || self.fields.origin != rp.origin {
+            return Err(WebAuthnError::InvalidClientData);
+        }
+
+        Ok(())
+    }
+}
+
+// Credential created by a registration ceremony
+#[derive(Debug, PartialEq)]
+pub struct RegisteredCredential {
+    // Base64url encoded credential id
+    pub id: String,
+    // COSE_Key
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+// Attestation statements aren't checked: any authenticator is accepted ("none" attestation),
+// but the credential has to be created for this relying party with a key we can verify.
+pub fn verify_registration(
+    rp: &RelyingParty,
+    client_data: &ClientData,
+    attestation_object: &str,
+) -> Result<RegisteredCredential, WebAuthnError> {
+    client_data.verify(rp, "webauthn.create")?;
+
+    let attestation_object = decode(attestation_object)?;
+    let attestation_object: Value = ciborium::de::from_reader(attestation_object.as_slice())
+        .map_err(|_| WebAuthnError::InvalidEncoding)?;
+
+    let authenticator_data = attestation_object
+        .as_map()
+        .and_then(|map| map.iter().find(|(key, _)| key.as_text() == Some("authData")))
+        .and_then(|(_, value)| value.as_bytes())
+        .ok_or(WebAuthnError::InvalidEncoding)?;
+
+    let authenticator_data = AuthenticatorData::parse(authenticator_data, rp)?;
+
+    let (id, public_key) = authenticator_data
+        .attested_credential
+        .ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+
+    // Reject keys no login could ever be verified with
+    CosePublicKey::parse(&public_key)?;
+
+    Ok(RegisteredCredential {
+        id: URL_SAFE_NO_PAD.encode(id),
+        public_key,
+        sign_count: authenticator_data.sign_count,
+    })
+}
+
+// Verifies a login signed with the stored credential and returns the authenticator's new signature counter
+pub fn verify_assertion(
+    rp: &RelyingParty,
+    client_data: &ClientData,
+    authenticator_data: &str,
+    signature: &str,
+    public_key: &[u8],
+    stored_sign_count: u32,
+) -> Result<u32, WebAuthnError> {
+    client_data.verify(rp, "webauthn.get")?;
+
+    let raw_authenticator_data = decode(authenticator_data)?;
+    let signature = decode(signature)?;
+
+    let authenticator_data = AuthenticatorData::parse(&raw_authenticator_data, rp)?;
+
+    let mut signed_data = raw_authenticator_data;
+    signed_data.extend_from_slice(digest(&SHA256, &client_data.raw).as_ref());
+
+    CosePublicKey::parse(public_key)?.verify(&signed_data, &signature)?;
+
+    // Authenticators without a counter always report 0, every other one has to count up
+    let sign_count = authenticator_data.sign_count;
+    if (sign_count != 0 || stored_sign_count != 0) && sign_count <= stored_sign_count {
+        return Err(WebAuthnError::ClonedAuthenticator);
+    }
+
+    Ok(sign_count)
+}
+
+struct AuthenticatorData {
+    sign_count: u32,
+    // Credential id and COSE_Key, only present when a credential is registered
+    attested_credential: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl AuthenticatorData {
+    // rpIdHash (32) | flags (1) | signCount (4) | [aaguid (16) | idLength (2) | id | COSE_Key] | [extensions]
+    fn parse(data: &[u8], rp: &RelyingParty) -> Result<Self, WebAuthnError> {
+        if data.len() < 37 {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+
+        if data[..32] != *digest(&SHA256, rp.id.as_bytes()).as_ref() {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+
+        // A passkey login stands in for both the password and the second factor, so touching the
+        // authenticator isn't enough, the user has to have unlocked it with a PIN or biometric
+        let flags = data[32];
+        if flags & FLAG_USER_PRESENT == 0 || flags & FLAG_USER_VERIFIED == 0 {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+        if flags & FLAG_ATTESTED_CREDENTIAL_DATA == 0 {
+            return Ok(AuthenticatorData { sign_count, attested_credential: None });
+        }
+
+        let data = &data[37..];
+        if data.len() < 18 {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+
+        let id_length = u16::from_be_bytes([data[16], data[17]]) as usize;
+        let data = &data[18..];
+        if data.len() < id_length {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+
+        let (id, key_and_extensions) = data.split_at(id_length);
+
+        // The key has no length prefix, so decode it to find where the extensions start
+        let mut remaining = key_and_extensions;
+        let _: Value = ciborium::de::from_reader(&mut remaining)
+            .map_err(|_| WebAuthnError::InvalidAuthenticatorData)?;
+        let public_key = &key_and_extensions[..key_and_extensions.len() - remaining.len()];
+
+        Ok(AuthenticatorData {
+            sign_count,
+            attested_credential: Some((id.to_vec(), public_key.to_vec())),
+        })
+    }
+}
+
+enum CosePublicKey {
+    Es256 { point: Vec<u8> },
+    EdDsa { x: Vec<u8> },
+    Rs256 { n: Vec<u8>, e: Vec<u8> },
+}
+
+impl CosePublicKey {
+    fn parse(encoded: &[u8]) -> Result<Self, WebAuthnError> {
+        let key: Value = ciborium::de::from_reader(encoded).map_err(|_| WebAuthnError::InvalidEncoding)?;
+        let key = key.as_map().ok_or(WebAuthnError::InvalidEncoding)?;
+
+        let parameter = |label: i64| {
+            key.iter()
+                .find(|(key, _)| key.as_integer().map(i128::from) == Some(label as i128))
+                .map(|(_, value)| value)
+        };
+        let bytes = |label: i64| {
+            parameter(label)
+                .and_then(Value::as_bytes)
+                .cloned()
+                .ok_or(WebAuthnError::InvalidEncoding)
+        };
+
+        let integer = |label: i64| {
+            parameter(label)
+                .and_then(Value::as_integer)
+                .map(i128::from)
+                .ok_or(WebAuthnError::InvalidEncoding)
+        };
+
+        // Label 1 is the key type, label 3 the algorithm, negative labels are the key type specific parameters.
+        // They have to agree, a key on the wrong curve would only be noticed once a login fails.
+        let key_type = integer(1)?;
+        let algorithm = integer(3)?;
+
+        match algorithm {
+            a if a == COSE_ALGORITHM_ES256 as i128 => {
+                if key_type != COSE_KEY_TYPE_EC2 || integer(-1)? != COSE_CURVE_P256 {
+                    return Err(WebAuthnError::InvalidEncoding);
+                }
+
+                let (x, y) = (bytes(-2)?, bytes(-3)?);
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(WebAuthnError::InvalidEncoding);
+                }
+
+                // Uncompressed SEC1 point
+                let mut point = vec![0x04];
+                point.extend_from_slice(&x);
+                point.extend_from_slice(&y);
+
+                Ok(CosePublicKey::Es256 { point })
+            }
+            a if a == COSE_ALGORITHM_EDDSA as i128 => {
+                if key_type != COSE_KEY_TYPE_OKP || integer(-1)? != COSE_CURVE_ED25519 {
+                    return Err(WebAuthnError::InvalidEncoding);
+                }
+
+                let x = bytes(-2)?;
+                if x.len() != 32 {
+                    return Err(WebAuthnError::InvalidEncoding);
+                }
+
+                Ok(CosePublicKey::EdDsa { x })
+            }
+            a if a == COSE_ALGORITHM_RS256 as i128 => {
+                if key_type != COSE_KEY_TYPE_RSA {
+                    return Err(WebAuthnError::InvalidEncoding);
+                }
+
+                Ok(CosePublicKey::Rs256 { n: bytes(-1)?, e: bytes(-2)? })
+            }
+            _ => Err(WebAuthnError::UnsupportedAlgorithm),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), WebAuthnError> {
+        let result = match self {
+            CosePublicKey::Es256 { point } =>
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point).verify(message, signature),
+            CosePublicKey::EdDsa { x } =>
+                UnparsedPublicKey::new(&ED25519, x).verify(message, signature),
+            CosePublicKey::Rs256 { n, e } =>
+                RsaPublicKeyComponents { n, e }.verify(&RSA_PKCS1_2048_8192_SHA256, message, signature),
+        };
+
+        result.map_err(|_| WebAuthnError::InvalidSignature)
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, WebAuthnError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| WebAuthnError::InvalidEncoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    use super::*;
+
+    const CREDENTIAL_ID: &[u8] = b"credential-id";
+
+    fn rp() -> RelyingParty {
+        RelyingParty {
+            id: "localhost".to_owned(),
+            name: "auth-service".to_owned(),
+            origin: "http://localhost:8000".to_owned(),
+        }
+    }
+
+    fn encode_cbor(value: &Value) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(value, &mut encoded).unwrap();
+        encoded
+    }
+
+    fn client_data_json(ceremony_type: &str, origin: &str) -> String {
+        let json = serde_json::json!({
+            "type": ceremony_type,
+            "challenge": "challenge",
+            "origin": origin,
+        });
+        URL_SAFE_NO_PAD.encode(json.to_string())
+    }
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32, attested_key: Option<&[u8]>) -> Vec<u8> {
+        let mut data = digest(&SHA256, rp_id.as_bytes()).as_ref().to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+
+        if let Some(key) = attested_key {
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+            data.extend_from_slice(CREDENTIAL_ID);
+            data.extend_from_slice(key);
+        }
+
+        data
+    }
+
+    fn attestation_object(authenticator_data: Vec<u8>) -> String {
+        let object = Value::Map(vec![
+            (Value::Text("fmt".to_owned()), Value::Text("none".to_owned())),
+            (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+            (Value::Text("authData".to_owned()), Value::Bytes(authenticator_data)),
+        ]);
+        URL_SAFE_NO_PAD.encode(encode_cbor(&object))
+    }
+
+    fn ed25519_key() -> (Ed25519KeyPair, Vec<u8>) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+        let cose_key = encode_cbor(&Value::Map(vec![
+            (Value::from(1), Value::from(1)),
+            (Value::from(3), Value::from(COSE_ALGORITHM_EDDSA)),
+            (Value::from(-1), Value::from(6)),
+            (Value::from(-2), Value::Bytes(key_pair.public_key().as_ref().to_vec())),
+        ]));
+
+        (key_pair, cose_key)
+    }
+
+    fn es256_key() -> (EcdsaKeyPair, Vec<u8>) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+
+        let point = key_pair.public_key().as_ref();
+        let cose_key = encode_cbor(&Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(COSE_ALGORITHM_ES256)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(point[1..33].to_vec())),
+            (Value::from(-3), Value::Bytes(point[33..].to_vec())),
+        ]));
+
+        (key_pair, cose_key)
+    }
+
+    fn signed_data(authenticator_data: &[u8], client_data: &str) -> Vec<u8> {
+        let mut data = authenticator_data.to_vec();
+        data.extend_from_slice(digest(&SHA256, &decode(client_data).unwrap()).as_ref());
+        data
+    }
+
+    #[test]
+    fn test_verify_registration() {
+        let (_, cose_key) = ed25519_key();
+        let client_data = ClientData::parse(&client_data_json("webauthn.create", "http://localhost:8000")).unwrap();
+
+        assert_eq!(client_data.challenge(), "challenge");
+
+        let data = authenticator_data("localhost", FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA, 0, Some(&cose_key));
+
+        assert_eq!(
+            verify_registration(&rp(), &client_data, &attestation_object(data)),
+            Ok(RegisteredCredential {
+                id: URL_SAFE_NO_PAD.encode(CREDENTIAL_ID),
+                public_key: cose_key,
+                sign_count: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_registration_with_extensions() {
+        let (_, cose_key) = ed25519_key();
+        let client_data = ClientData::parse(&client_data_json("webauthn.create", "http://localhost:8000")).unwrap();
+
+        // Extensions follow the key without a length prefix and must not end up in the stored key
+        let mut data = authenticator_data("localhost", FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA | 0x80, 0, Some(&cose_key));
+        data.extend_from_slice(&encode_cbor(&Value::Map(vec![
+            (Value::Text("credProtect".to_owned()), Value::from(1)),
+        ])));
+
+        let registered = verify_registration(&rp(), &client_data, &attestation_object(data)).unwrap();
+
+        assert_eq!(registered.public_key, cose_key);
+    }
+
+    #[test]
+    fn test_verify_registration_for_another_site() {
+        let (_, cose_key) = ed25519_key();
+        let flags = FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA;
+
+        let client_data = ClientData::parse(&client_data_json("webauthn.create", "http://evil.example.com")).unwrap();
+        let data = authenticator_data("localhost", flags, 0, Some(&cose_key));
+        assert_eq!(
+            verify_registration(&rp(), &client_data, &attestation_object(data)),
+            Err(WebAuthnError::InvalidClientData)
+        );
+
+        let client_data = ClientData::parse(&client_data_json("webauthn.create", "http://localhost:8000")).unwrap();
+        let data = authenticator_data("evil.example.com", flags, 0, Some(&cose_key));
+        assert_eq!(
+            verify_registration(&rp(), &client_data, &attestation_object(data)),
+            Err(WebAuthnError::InvalidAuthenticatorData)
+        );
+    }
+
+    #[test]
+    fn test_verify_registration_with_unsupported_key() {
+        let cose_key = encode_cbor(&Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(-35)),
+        ]));
+        let client_data = ClientData::parse(&client_data_json("webauthn.create", "http://localhost:8000")).unwrap();
+        let data = authenticator_data("localhost", FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA, 0, Some(&cose_key));
+
+        assert_eq!(
+            verify_registration(&rp(), &client_data, &attestation_object(data)),
+            Err(WebAuthnError::UnsupportedAlgorithm)
+        );
+    }
+
+    // Encodes a COSE key with the given parameters, the key bytes themselves are only checked for their length
+    fn cose_key(key_type: i64, algorithm: i64, curve: i64, x_length: usize) -> Vec<u8> {
+        encode_cbor(&Value::Map(vec![
+            (Value::from(1), Value::from(key_type)),
+            (Value::from(3), Value::from(algorithm)),
+            (Value::from(-1), Value::from(curve)),
+            (Value::from(-2), Value::Bytes(vec![1; x_length])),
+            (Value::from(-3), Value::Bytes(vec![1; 32])),
+        ]))
+    }
+
+    fn parse_cose_key(encoded: &[u8]) -> Option<WebAuthnError> {
+        CosePublicKey::parse(encoded).err()
+    }
+
+    #[test]
+    fn test_parse_cose_key() {
+        assert_eq!(parse_cose_key(&cose_key(2, COSE_ALGORITHM_ES256, 1, 32)), None);
+        assert_eq!(parse_cose_key(&cose_key(1, COSE_ALGORITHM_EDDSA, 6, 32)), None);
+    }
+
+    #[test]
+    fn test_parse_es256_key_with_wrong_key_type() {
+        assert_eq!(parse_cose_key(&cose_key(1, COSE_ALGORITHM_ES256, 1, 32)), Some(WebAuthnError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_parse_es256_key_with_wrong_curve() {
+        // P-384
+        assert_eq!(parse_cose_key(&cose_key(2, COSE_ALGORITHM_ES256, 2, 32)), Some(WebAuthnError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_parse_eddsa_key_with_wrong_key_type() {
+        assert_eq!(parse_cose_key(&cose_key(2, COSE_ALGORITHM_EDDSA, 6, 32)), Some(WebAuthnError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_parse_eddsa_key_with_wrong_curve() {
+        // Ed448
+        assert_eq!(parse_cose_key(&cose_key(1, COSE_ALGORITHM_EDDSA, 7, 32)), Some(WebAuthnError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_parse_eddsa_key_with_wrong_length() {
+        assert_eq!(parse_cose_key(&cose_key(1, COSE_ALGORITHM_EDDSA, 6, 57)), Some(WebAuthnError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_parse_rs256_key_with_wrong_key_type() {
+        assert_eq!(parse_cose_key(&cose_key(2, COSE_ALGORITHM_RS256, 1, 32)), Some(WebAuthnError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_verify_registration_with_mismatched_key() {
+        let client_data = ClientData::parse(&client_data_json("webauthn.create", "http://localhost:8000")).unwrap();
+        let cose_key = cose_key(1, COSE_ALGORITHM_EDDSA, 7, 57);
+        let data = authenticator_data("localhost", FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA, 0, Some(&cose_key));
+
+        assert_eq!(
+            verify_registration(&rp(), &client_data, &attestation_object(data)),
+            Err(WebAuthnError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn test_verify_ed25519_assertion() {
+        let (key_pair, cose_key) = ed25519_key();
+        let encoded_client_data = client_data_json("webauthn.get", "http://localhost:8000");
+        let client_data = ClientData::parse(&encoded_client_data).unwrap();
+
+        let data = authenticator_data("localhost", FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 0, None);
+        let signature = key_pair.sign(&signed_data(&data, &encoded_client_data));
+
+        assert_eq!(
+            verify_assertion(
+                &rp(),
+                &client_data,
+                &URL_SAFE_NO_PAD.encode(&data),
+                &URL_SAFE_NO_PAD.encode(signature),
+                &cose_key,
+                0,
+            ),
+            Ok(0)
+        );
+
+        // A signature over different client data doesn't verify
+        let other_signature = key_pair.sign(&data);
+        assert_eq!(
+            verify_assertion(
+                &rp(),
+                &client_data,
+                &URL_SAFE_NO_PAD.encode(&data),
+                &URL_SAFE_NO_PAD.encode(other_signature),
+                &cose_key,
+                0,
+            ),
+            Err(WebAuthnError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_es256_assertion_sign_count() {
+        let (key_pair, cose_key) = es256_key();
+        let encoded_client_data = client_data_json("webauthn.get", "http://localhost:8000");
+        let client_data = ClientData::parse(&encoded_client_data).unwrap();
+
+        let data = authenticator_data("localhost", FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 5, None);
+        let signature = key_pair
+            .sign(&SystemRandom::new(), &signed_data(&data, &encoded_client_data))
+            .unwrap();
+
+        let verify = |stored_sign_count| verify_assertion(
+            &rp(),
+            &client_data,
+            &URL_SAFE_NO_PAD.encode(&data),
+            &URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            &cose_key,
+            stored_sign_count,
+        );
+
+        assert_eq!(verify(4), Ok(5));
+        assert_eq!(verify(5), Err(WebAuthnError::ClonedAuthenticator));
+    }
+
+    #[test]
+    fn test_verify_assertion_without_user_presence() {
+        let (key_pair, cose_key) = ed25519_key();
+        let encoded_client_data = client_data_json("webauthn.get", "http://localhost:8000");
+        let client_data = ClientData::parse(&encoded_client_data).unwrap();
+
+        let data = authenticator_data("localhost", FLAG_USER_VERIFIED, 0, None);
+        let signature = key_pair.sign(&signed_data(&data, &encoded_client_data));
+
+        assert_eq!(
+            verify_assertion(
+                &rp(),
+                &client_data,
+                &URL_SAFE_NO_PAD.encode(&data),
+                &URL_SAFE_NO_PAD.encode(signature),
+                &cose_key,
+                0,
+            ),
+            Err(WebAuthnError::InvalidAuthenticatorData)
+        );
+    }
+
+    #[test]
+    fn test_verify_assertion_without_user_verification() {
+        let (key_pair, cose_key) = ed25519_key();
+        let encoded_client_data = client_data_json("webauthn.get", "http://localhost:8000");
+        let client_data = ClientData::parse(&encoded_client_data).unwrap();
+
+        let data = authenticator_data("localhost", FLAG_USER_PRESENT, 0, None);
+        let signature = key_pair.sign(&signed_data(&data, &encoded_client_data));
+
+        assert_eq!(
+            verify_assertion(
+                &rp(),
+                &client_data,
+                &URL_SAFE_NO_PAD.encode(&data),
+                &URL_SAFE_NO_PAD.encode(signature),
+                &cose_key,
+                0,
+            ),
+            Err(WebAuthnError::InvalidAuthenticatorData)
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use auth_service::domain::{Email, ADMIN_ROLE};
+use auth_service::routes::{AdminUserListResponse, AdminUserResponse};
+use auth_service::utils::auth::MAX_LOGIN_FAILURES_PER_EMAIL;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+// Signs up a verified user without logging in, so the cookie jar keeps the admin's session
+async fn create_user(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(email).await;
+}
+
+async fn login_as_admin(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    create_user(app, &email).await;
+
+    app.user_store
+        .write()
+        .await
+        .set_roles(&Email::parse(&email).unwrap(), vec![ADMIN_ROLE.to_owned()])
+        .await
+        .expect("Failed to grant the admin role");
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.get_admin_users("").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_403_if_not_an_admin() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    create_user(&app, &random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_admin_users("").await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.post_admin_user_action(&random_email, "disable").await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_list_users_with_search_and_pagination() {
+    let app = TestApp::new().await;
+
+    login_as_admin(&app).await;
+
+    // A marker shared by the users of this test keeps the search independent of other data
+    let marker = uuid::Uuid::new_v4().simple().to_string();
+
+    let emails: Vec<String> = (0..3).map(|i| format!("user{}.{}@example.com", i, marker)).collect();
+
+    for email in &emails {
+        create_user(&app, email).await;
+    }
+
+    let response = app.get_admin_users(&format!("email={}&perPage=2", marker.to_uppercase())).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let first_page = response
+        .json::<AdminUserListResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserListResponse");
+
+    assert_eq!(first_page.total, 3);
+    assert_eq!(first_page.page, 1);
+    assert_eq!(first_page.per_page, 2);
+    assert_eq!(
+        first_page.users.iter().map(|user| user.email.clone()).collect::<Vec<_>>(),
+        emails[..2].to_vec()
+    );
+
+    let response = app.get_admin_users(&format!("email={}&perPage=2&page=2", marker)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let second_page = response
+        .json::<AdminUserListResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserListResponse");
+
+    assert_eq!(second_page.total, 3);
+    assert_eq!(
+        second_page.users.iter().map(|user| user.email.clone()).collect::<Vec<_>>(),
+        emails[2..].to_vec()
+    );
+}
+
+#[api_test]
+async fn should_return_user_details() {
+    let app = TestApp::new().await;
+
+    let admin_email = login_as_admin(&app).await;
+
+    let response = app.get_admin_user(&admin_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert_eq!(user.email, admin_email);
+    assert!(user.roles.contains(&ADMIN_ROLE.to_owned()));
+    assert_eq!(user.status, "active");
+    assert_eq!(user.locked_out_seconds, None);
+}
+
+#[api_test]
+async fn should_return_404_if_user_not_found() {
+    let app = TestApp::new().await;
+
+    login_as_admin(&app).await;
+
+    let random_email = get_random_email();
+
+    let response = app.get_admin_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.post_admin_user_action(&random_email, "disable").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.delete_admin_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[api_test]
+async fn should_block_login_while_suspended() {
+    let app = TestApp::new().await;
+
+    login_as_admin(&app).await;
+
+    let random_email = get_random_email();
+
+    create_user(&app, &random_email).await;
+
+    let response = app.post_admin_user_action(&random_email, "disable").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert_eq!(user.status, "suspended");
+
+    let response = app.post_admin_user_action(&random_email, "enable").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert_eq!(user.status, "active");
+
+    let response = app.post_admin_user_action(&random_email, "disable").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_require_a_new_password_after_forced_reset() {
+    let app = TestApp::new().await;
+
+    login_as_admin(&app).await;
+
+    let random_email = get_random_email();
+
+    create_user(&app, &random_email).await;
+
+    let response = app.post_admin_user_action(&random_email, "force-password-reset").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert!(user.password_reset_required);
+
+    let emails = app.email_outbox
+        .read()
+        .await
+        .get_emails(&Email::parse(&random_email).unwrap())
+        .await
+        .expect("Failed to read the outbox");
+
+    assert!(emails.iter().any(|email| email.message.subject.contains("password")));
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_toggle_requires_2fa() {
+    let app = TestApp::new().await;
+
+    login_as_admin(&app).await;
+
+    let random_email = get_random_email();
+
+    create_user(&app, &random_email).await;
+
+    let response = app.post_admin_requires_2fa(&random_email, &serde_json::json!({ "requires2FA": true })).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert!(user.requires_2fa);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[api_test]
+async fn should_unlock_a_locked_out_user() {
+    let app = TestApp::new().await;
+
+    login_as_admin(&app).await;
+
+    let random_email = get_random_email();
+
+    create_user(&app, &random_email).await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrongpassword",
+    });
+
+    for _ in 0..MAX_LOGIN_FAILURES_PER_EMAIL {
+        let response = app.post_login(&wrong_login_body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.get_admin_user(&random_email).await;
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert!(user.locked_out_seconds.is_some());
+
+    let response = app.post_admin_user_action(&random_email, "unlock").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_delete_user() {
+    let app = TestApp::new().await;
+
+    login_as_admin(&app).await;
+
+    let random_email = get_random_email();
+
+    create_user(&app, &random_email).await;
+
+    let response = app.delete_admin_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_admin_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,188 @@
+use auth_service::routes::SessionsResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use reqwest::Url;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    random_email
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": password,
+    });
+
+    app.post_login(&login_body).await
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_401_if_current_password_incorrect() {
+    let app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    assert_eq!(login(&app, &email, "password123").await.status().as_u16(), 200);
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "wrong-password",
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_change_password() {
+    let app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    assert_eq!(login(&app, &email, "password123").await.status().as_u16(), 200);
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(login(&app, &email, "password123").await.status().as_u16(), 401);
+    assert_eq!(login(&app, &email, "new-password123").await.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_revoke_other_sessions_if_requested() {
+    let app = TestApp::new().await;
+
+    let email = signup(&app).await;
+
+    let response = login(&app, &email, "password123").await;
+    let other_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+    let other_token = other_cookie.value().to_owned();
+
+    assert_eq!(login(&app, &email, "password123").await.status().as_u16(), 200);
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new-password123",
+            "revokeOtherSessions": true,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": other_token })).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json_body = app
+        .get_sessions()
+        .await
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse");
+
+    assert_eq!(json_body.sessions.len(), 1);
+    assert!(json_body.sessions[0].current);
+}
+
+#[api_test]
+async fn should_revoke_refresh_tokens_of_lapsed_sessions() {
+    let app = TestApp::new().await;
+
+    let email = signup(&app).await;
+
+    let response = login(&app, &email, "password123").await;
+    let other_refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("Refresh cookie not found")
+        .value()
+        .to_owned();
+
+    // Drop the other session's record, as if its access token had expired without the refresh token
+    let other_session_id = app
+        .get_sessions()
+        .await
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions[0]
+        .id
+        .clone();
+
+    app.session_store.write().await.remove_session(&other_session_id).await.unwrap();
+
+    assert_eq!(login(&app, &email, "password123").await.status().as_u16(), 200);
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new-password123",
+            "revokeOtherSessions": true,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The caller got fresh cookies and stays logged in
+    let json_body = app
+        .get_sessions()
+        .await
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse");
+
+    assert_eq!(json_body.sessions.len(), 1);
+    assert!(json_body.sessions[0].current);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME,
+            other_refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -1,22 +1,41 @@
 use std::str::FromStr;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use uuid::Uuid;
 use std::sync::Arc;
 use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderValue};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
-use auth_service::{Application, AppState, MockEmailClient, get_postgres_pool,
+use auth_service::{Application, AppState, get_postgres_pool,
                    PostgresUserStore, RedisBannedTokenStore, get_redis_client,
-                   RedisTwoFACodeStore};
-use tokio::sync::RwLock;
-use auth_service::app_state::{BannedTokenStoreType, TwoFACodeStoreType};
-use auth_service::utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME};
+                   RedisTwoFACodeStore, RedisRefreshTokenStore, RedisSessionStore,
+                   RedisPasswordResetTokenStore, RedisEmailVerificationTokenStore, PostgresCredentialStore,
+                   RedisWebAuthnChallengeStore, RedisLoginAttemptStore, RedisRateLimitStore, PostgresEmailOutboxStore,
+                   PostgresAuditLogStore};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, RwLock};
+use auth_service::app_state::{
+    AuditLogStoreType, BannedTokenStoreType, CredentialStoreType, EmailOutboxStoreType, EmailVerificationTokenStoreType,
+    PasswordResetTokenStoreType, SessionStoreType, TwoFACodeStoreType, UserStoreType,
+};
+use auth_service::domain::Email;
+use auth_service::utils::constants::{test, DATABASE_URL, REAL_IP_HEADER, REDIS_HOST_NAME};
 
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
+    pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub session_store: SessionStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub credential_store: CredentialStoreType,
+    pub email_outbox: EmailOutboxStoreType,
+    pub audit_log_store: AuditLogStoreType,
     pub db_name: String,
     pub clean_up_called: bool
 }
@@ -33,7 +52,7 @@ impl TestApp {
         //     Arc::new(RwLock::new(HashmapUserStore::default()));
 
         let user_store =
-            Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+            Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
 
         // let banned_token_store =
         //     Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
@@ -45,13 +64,75 @@ impl TestApp {
         //     Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
 
         let two_fa_code_store =
-            Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+            Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
 
-        let email_client =
-            Arc::new(MockEmailClient);
+        // let refresh_token_store =
+        //     Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
 
-        let app_state = AppState::new(user_store, banned_token_store.clone(),
-                                      two_fa_code_store.clone(), email_client.clone());
+        let refresh_token_store =
+            Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+
+        // let session_store =
+        //     Arc::new(RwLock::new(HashmapSessionStore::default()));
+
+        let session_store =
+            Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
+
+        // let password_reset_token_store =
+        //     Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+
+        let password_reset_token_store =
+            Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn.clone())));
+
+        // let email_verification_token_store =
+        //     Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default()));
+
+        let email_verification_token_store =
+            Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(redis_conn.clone())));
+
+        // let credential_store =
+        //     Arc::new(RwLock::new(HashmapCredentialStore::default()));
+
+        let credential_store =
+            Arc::new(RwLock::new(PostgresCredentialStore::new(pg_pool.clone())));
+
+        // let webauthn_challenge_store =
+        //     Arc::new(RwLock::new(HashmapWebAuthnChallengeStore::default()));
+
+        let webauthn_challenge_store =
+            Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(redis_conn.clone())));
+
+        // let login_attempt_store =
+        //     Arc::new(RwLock::new(HashmapLoginAttemptStore::default()));
+
+        let login_attempt_store =
+            Arc::new(RwLock::new(RedisLoginAttemptStore::new(redis_conn.clone())));
+
+        // let rate_limit_store =
+        //     Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+
+        let rate_limit_store =
+            Arc::new(RwLock::new(RedisRateLimitStore::new(redis_conn)));
+
+        // let email_outbox =
+        //     Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+
+        // No worker delivers the queued emails, tests look at the outbox instead
+        let email_outbox =
+            Arc::new(RwLock::new(PostgresEmailOutboxStore::new(pg_pool.clone())));
+
+        // let audit_log_store =
+        //     Arc::new(RwLock::new(HashmapAuditLogStore::default()));
+
+        let audit_log_store =
+            Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool)));
+
+        let app_state = AppState::new(user_store.clone(), banned_token_store.clone(),
+                                      two_fa_code_store.clone(), refresh_token_store,
+                                      session_store.clone(), password_reset_token_store.clone(),
+                                      email_verification_token_store.clone(), credential_store.clone(),
+                                      webauthn_challenge_store, login_attempt_store, rate_limit_store,
+                                      email_outbox.clone(), audit_log_store.clone());
 
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -64,14 +145,52 @@ impl TestApp {
         #[allow(clippy::let_underscore_future)]
         let _ = tokio::spawn(app.run());
 
+        // Failed logins are also counted per IP address, so every test app poses as its own client
+        // to keep tests running in parallel from locking each other out. Loopback is a trusted proxy by default.
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(REAL_IP_HEADER, HeaderValue::from_str(&get_random_ip()).unwrap());
+
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
             .cookie_provider(cookie_jar.clone())
+            .default_headers(default_headers)
             .build()
             .unwrap();
 
-        Self { address, cookie_jar, http_client, banned_token_store, two_fa_code_store, db_name,
-            clean_up_called: false, }
+        Self { address, cookie_jar, http_client, user_store, banned_token_store, two_fa_code_store, session_store,
+            password_reset_token_store, email_verification_token_store, credential_store, email_outbox,
+            audit_log_store, db_name, clean_up_called: false, }
+    }
+
+    // Verification links are emailed, so tests which need to log in mark the user as verified directly
+    pub async fn verify_email(&self, email: &str) {
+        self.user_store
+            .write()
+            .await
+            .mark_email_verified(&Email::parse(email).unwrap())
+            .await
+            .expect("Failed to verify email");
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email/resend", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
@@ -114,6 +233,185 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_request<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/request", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_introspect(&self, token: &str, credentials: Option<(&str, &str)>) -> reqwest::Response {
+        let mut request = self.http_client
+            .post(format!("{}/introspect", &self.address))
+            .form(&[("token", token)]);
+
+        if let Some((client_id, client_secret)) = credentials {
+            request = request.basic_auth(client_id, Some(client_secret));
+        }
+
+        request
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_begin(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/webauthn/register/begin", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/webauthn/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_begin<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/webauthn/login/begin", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/webauthn/login/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/2fa/totp/enroll", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_regenerate_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/recovery-codes", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -138,6 +436,70 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_me_export(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/me/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_me<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .delete(format!("{}/me", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users(&self, query: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_user_action(&self, email: &str, action: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/users/{}/{}", &self.address, email, action))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_requires_2fa<Body>(&self, email: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/users/{}/requires-2fa", &self.address, email))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn clean_up(&mut self) {
 
         if self.clean_up_called {
@@ -162,6 +524,11 @@ pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
+pub fn get_random_ip() -> String {
+    let bytes = Uuid::new_v4().into_bytes();
+    format!("10.{}.{}.{}", bytes[0], bytes[1], bytes[2])
+}
+
 async fn configure_postgresql(db_name: &str) -> PgPool {
     let postgresql_conn_url = DATABASE_URL.to_owned();
 
@@ -242,3 +609,120 @@ fn configure_redis() -> redis::Connection {
         .get_connection()
         .expect("Failed to get Redis connection")
 }
+
+// How the fake SMTP server answers
+#[derive(Clone, Copy, PartialEq)]
+pub enum FakeSmtpBehaviour {
+    Accept,
+    RejectRecipients,
+    // Accepts connections but never greets, so clients run into their timeout
+    Stall,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReceivedEmail {
+    pub from: String,
+    pub recipients: Vec<String>,
+    // Decoded `AUTH PLAIN` credentials as `username:password`
+    pub credentials: Option<String>,
+    pub data: String,
+}
+
+// Plain text SMTP server on localhost recording every message it accepts,
+// so the SMTP email client can be tested without network access
+pub struct FakeSmtpServer {
+    pub port: u16,
+    pub emails: Arc<Mutex<Vec<ReceivedEmail>>>,
+}
+
+impl FakeSmtpServer {
+    pub async fn start(behaviour: FakeSmtpBehaviour) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind fake SMTP server");
+        let port = listener.local_addr().unwrap().port();
+        let emails = Arc::new(Mutex::new(Vec::new()));
+
+        let server_emails = emails.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let emails = server_emails.clone();
+                tokio::spawn(async move {
+                    let _ = handle_smtp_connection(stream, behaviour, emails).await;
+                });
+            }
+        });
+
+        FakeSmtpServer { port, emails }
+    }
+}
+
+async fn handle_smtp_connection(
+    stream: TcpStream,
+    behaviour: FakeSmtpBehaviour,
+    emails: Arc<Mutex<Vec<ReceivedEmail>>>,
+) -> std::io::Result<()> {
+    if behaviour == FakeSmtpBehaviour::Stall {
+        std::future::pending::<()>().await;
+    }
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut email = ReceivedEmail::default();
+
+    writer.write_all(b"220 localhost ESMTP\r\n").await?;
+
+    while let Some(line) = lines.next_line().await? {
+        let command = line.to_ascii_uppercase();
+
+        let reply = if command.starts_with("EHLO") {
+            "250-localhost\r\n250 AUTH PLAIN LOGIN".to_owned()
+        } else if command.starts_with("AUTH PLAIN ") {
+            let decoded = STANDARD.decode(line[11..].trim()).unwrap_or_default();
+            let parts: Vec<String> = decoded
+                .split(|byte| *byte == 0)
+                .map(|part| String::from_utf8_lossy(part).into_owned())
+                .collect();
+            email.credentials = parts.get(1..3).map(|parts| parts.join(":"));
+            "235 2.7.0 Authentication successful".to_owned()
+        } else if command.starts_with("MAIL FROM:") {
+            email.from = smtp_address(&line);
+            "250 OK".to_owned()
+        } else if command.starts_with("RCPT TO:") {
+            match behaviour {
+                FakeSmtpBehaviour::RejectRecipients => "550 5.1.1 No such user".to_owned(),
+                _ => {
+                    email.recipients.push(smtp_address(&line));
+                    "250 OK".to_owned()
+                },
+            }
+        } else if command == "DATA" {
+            writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await?;
+
+            while let Some(line) = lines.next_line().await? {
+                if line == "." {
+                    break;
+                }
+                email.data.push_str(line.strip_prefix('.').unwrap_or(&line));
+                email.data.push('\n');
+            }
+
+            emails.lock().await.push(std::mem::take(&mut email));
+            "250 OK".to_owned()
+        } else if command == "QUIT" {
+            writer.write_all(b"221 Bye\r\n").await?;
+            break;
+        } else if command == "RSET" || command == "NOOP" {
+            "250 OK".to_owned()
+        } else {
+            "502 Command not implemented".to_owned()
+        };
+
+        writer.write_all(format!("{}\r\n", reply).as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+// Address between the angle brackets of `MAIL FROM:<...>` and `RCPT TO:<...>`
+fn smtp_address(line: &str) -> String {
+    line.split(['<', '>']).nth(1).unwrap_or_default().to_owned()
+}
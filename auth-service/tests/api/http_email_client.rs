@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use auth_service::domain::{Email, EmailClient, EmailClientError, EmailMessage};
+use auth_service::{HttpEmailClient, HttpEmailConfig};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use crate::helpers::get_random_email;
+
+fn http_email_config(base_url: String) -> HttpEmailConfig {
+    HttpEmailConfig {
+        base_url,
+        token: "api-token".to_owned(),
+        from: "no-reply@example.com".to_owned(),
+        timeout: Duration::from_millis(500),
+        max_retries: 2,
+        retry_backoff: Duration::from_millis(10),
+    }
+}
+
+fn message() -> EmailMessage {
+    EmailMessage {
+        subject: "Your 2FA Code".to_owned(),
+        html: "<p>123456</p>".to_owned(),
+        text: "123456".to_owned(),
+    }
+}
+
+async fn send_email(server: &MockServer) -> Result<(), EmailClientError> {
+    HttpEmailClient::new(&http_email_config(server.uri()))
+        .unwrap()
+        .send_email(&Email::parse(&get_random_email()).unwrap(), &message())
+        .await
+}
+
+#[tokio::test]
+async fn should_send_email_to_api() {
+    let server = MockServer::start().await;
+    let recipient = get_random_email();
+
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .and(header("X-Postmark-Server-Token", "api-token"))
+        .and(header("Content-Type", "application/json"))
+        .and(body_partial_json(serde_json::json!({
+            "From": "no-reply@example.com",
+            "To": recipient,
+            "Subject": "Your 2FA Code",
+            "HtmlBody": "<p>123456</p>",
+            "TextBody": "123456",
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = HttpEmailClient::new(&http_email_config(server.uri()))
+        .unwrap()
+        .send_email(&Email::parse(&recipient).unwrap(), &message())
+        .await;
+
+    assert_eq!(result, Ok(()));
+}
+
+#[tokio::test]
+async fn should_retry_on_server_error() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert_eq!(send_email(&server).await, Ok(()));
+}
+
+#[tokio::test]
+async fn should_return_unavailable_once_retries_exhausted() {
+    let server = MockServer::start().await;
+
+    // The first attempt and both retries
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let result = send_email(&server).await;
+
+    assert!(matches!(result, Err(EmailClientError::Unavailable(_))));
+    assert!(result.unwrap_err().is_retryable());
+}
+
+#[tokio::test]
+async fn should_return_rejected_without_retrying_on_client_error() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(422).set_body_string("Inactive recipient"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = send_email(&server).await;
+
+    assert!(matches!(result, Err(EmailClientError::Rejected(ref e)) if e.contains("Inactive recipient")));
+    assert!(!result.unwrap_err().is_retryable());
+}
+
+#[tokio::test]
+async fn should_return_unavailable_if_api_times_out() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let result = send_email(&server).await;
+
+    assert!(matches!(result, Err(EmailClientError::Unavailable(_))));
+}
@@ -0,0 +1,112 @@
+use auth_service::routes::IntrospectResponse;
+use auth_service::utils::constants::{INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET, JWT_COOKIE_NAME};
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+fn credentials() -> (&'static str, &'static str) {
+    let client_secret = INTROSPECTION_CLIENT_SECRET
+        .as_deref()
+        .expect("INTROSPECTION_CLIENT_SECRET must be set to run introspection tests.");
+
+    (INTROSPECTION_CLIENT_ID.as_str(), client_secret)
+}
+
+async fn login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    (random_email, cookie.value().to_owned())
+}
+
+#[api_test]
+async fn should_return_401_without_client_credentials() {
+    let app = TestApp::new().await;
+
+    let (_, token) = login(&app).await;
+
+    let response = app.post_introspect(&token, None).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let (client_id, _) = credentials();
+
+    let response = app.post_introspect(&token, Some((client_id, "wrong-secret"))).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_active_token() {
+    let app = TestApp::new().await;
+
+    let (email, token) = login(&app).await;
+
+    let response = app.post_introspect(&token, Some(credentials())).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+
+    assert!(json_body.active);
+    assert_eq!(json_body.sub, Some(email));
+    assert!(json_body.jti.is_some());
+}
+
+#[api_test]
+async fn should_return_inactive_if_token_invalid_or_banned() {
+    let app = TestApp::new().await;
+
+    let response = app.post_introspect("invalid token", Some(credentials())).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+
+    assert!(!json_body.active);
+    assert!(json_body.sub.is_none());
+
+    let (_, token) = login(&app).await;
+
+    app.post_logout().await;
+
+    let json_body = app
+        .post_introspect(&token, Some(credentials()))
+        .await
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+
+    assert!(!json_body.active);
+}
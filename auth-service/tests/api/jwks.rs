@@ -0,0 +1,21 @@
+use jsonwebtoken::jwk::JwkSet;
+use test_helpers::api_test;
+
+use crate::helpers::TestApp;
+
+#[api_test]
+async fn should_return_200_with_key_set() {
+    let app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    // Tests sign with the HS256 fallback, whose secret is never published
+    assert!(json_body.keys.is_empty());
+}
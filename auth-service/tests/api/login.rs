@@ -1,6 +1,8 @@
-use auth_service::domain::{Email, LoginAttemptId};
+use auth_service::domain::{Email, LoginAttemptId, OutboxEmailStatus};
 use auth_service::routes::TwoFactorAuthResponse;
-use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::utils::auth::{LOGIN_LOCKOUT_BASE_SECONDS, MAX_LOGIN_FAILURES_PER_EMAIL, MAX_LOGIN_FAILURES_PER_IP};
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use reqwest::header::RETRY_AFTER;
 use crate::helpers::{get_random_email, TestApp};
 
 use test_helpers::api_test;
@@ -61,6 +63,35 @@ async fn should_return_401_if_incorrect_credentials() {
     assert_eq!(response.status().as_u16(), 401);
 }
 
+#[api_test]
+async fn should_return_403_if_email_not_verified() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+}
+
 #[api_test]
 async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
@@ -78,6 +109,8 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -93,6 +126,13 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+
+    assert!(!refresh_cookie.value().is_empty());
 }
 
 #[api_test]
@@ -112,6 +152,8 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -144,3 +186,222 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
         login_attempt_id.0
     );
 }
+
+#[api_test]
+async fn should_queue_2fa_code_email() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    // Nothing delivers emails in tests, so the login can't depend on the email provider
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 206);
+
+    let email = Email::parse(&random_email).unwrap();
+
+    let (_, code) = app.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+
+    let emails = app.email_outbox.read().await.get_emails(&email).await.unwrap();
+    let two_fa_email = emails
+        .iter()
+        .find(|outbox_email| outbox_email.message.subject == "Your 2FA Code")
+        .expect("No 2FA email queued");
+
+    assert_eq!(two_fa_email.status, OutboxEmailStatus::Pending);
+    assert!(two_fa_email.message.text.contains(code.as_ref()));
+    assert!(two_fa_email.message.html.contains(code.as_ref()));
+}
+
+#[api_test]
+async fn should_queue_new_login_alert_only_for_unknown_clients() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    let alerts = app
+        .email_outbox
+        .read()
+        .await
+        .get_emails(&Email::parse(&random_email).unwrap())
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|outbox_email| outbox_email.message.subject == "New login to your account")
+        .count();
+
+    // The second login comes from the session the first one created
+    assert_eq!(alerts, 1);
+}
+
+#[api_test]
+async fn should_return_429_after_too_many_failed_logins() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrongpassword",
+    });
+
+    for _ in 0..MAX_LOGIN_FAILURES_PER_EMAIL {
+        let response = app.post_login(&wrong_login_body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the right password is turned away until the lockout ends
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: i64 = response
+        .headers()
+        .get(RETRY_AFTER)
+        .expect("No Retry-After header found")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    assert!(retry_after > 0 && retry_after <= LOGIN_LOCKOUT_BASE_SECONDS);
+
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+}
+
+#[api_test]
+async fn should_reset_failed_logins_after_successful_login() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrongpassword",
+    });
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    for _ in 0..2 {
+        for _ in 1..MAX_LOGIN_FAILURES_PER_EMAIL {
+            let response = app.post_login(&wrong_login_body).await;
+
+            assert_eq!(response.status().as_u16(), 401);
+        }
+
+        let response = app.post_login(&login_body).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[api_test]
+async fn should_return_429_if_ip_address_locked() {
+
+    let app = TestApp::new().await;
+
+    // Spread over many accounts, so only the per address limit is reached
+    for _ in 0..MAX_LOGIN_FAILURES_PER_IP {
+        let login_body = serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+        });
+
+        let response = app.post_login(&login_body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    assert!(response.headers().contains_key(RETRY_AFTER));
+}
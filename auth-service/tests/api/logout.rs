@@ -21,6 +21,8 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -74,6 +76,8 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -0,0 +1,112 @@
+use reqwest::Url;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+#[api_test]
+async fn should_invalidate_every_token_of_the_user() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    // Log in twice to get two independent sessions
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let first_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    let first_refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("Refresh cookie not found");
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let second_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    for token in [first_cookie.value(), second_cookie.value()] {
+        let verify_token_body = serde_json::json!({
+            "token": token,
+        });
+
+        let response = app.post_verify_token(&verify_token_body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The refresh token of the other session can't be used to get back in either
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME,
+            first_refresh_cookie.value()
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Logging in again works as usual
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    let app = TestApp::new().await;
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
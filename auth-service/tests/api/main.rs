@@ -1,7 +1,24 @@
+mod admin;
+mod change_password;
 mod helpers;
+mod http_email_client;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod me;
+mod password_reset;
+mod rate_limit;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
 mod root;
+mod sessions;
 mod signup;
+mod smtp_email_client;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webauthn;
@@ -0,0 +1,171 @@
+use auth_service::domain::Email;
+use auth_service::routes::AccountExportResponse;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.get_me_export().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_me(&serde_json::json!({ "password": "password123" })).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_export_account_data() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_me_export().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let export = response
+        .json::<AccountExportResponse>()
+        .await
+        .expect("Could not deserialize response body to AccountExportResponse");
+
+    assert_eq!(export.profile.email, random_email);
+    assert_eq!(export.profile.status, "active");
+    assert!(!export.two_factor.enabled);
+    assert!(export.two_factor.passkeys.is_empty());
+    assert_eq!(export.sessions.len(), 1);
+    assert!(export.sessions[0].current);
+    assert_eq!(export.audit_events.len(), 1);
+    assert_eq!(export.audit_events[0].event_type, "login");
+    assert!(export.audit_events[0].ip.is_some());
+    assert!(export.emails.iter().any(|email| email.subject == "New login to your account"));
+}
+
+#[api_test]
+async fn should_return_401_if_password_incorrect() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.delete_me(&serde_json::json!({ "password": "wrongpassword" })).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The account is still there
+    let response = app.get_me_export().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_delete_account() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found")
+        .value()
+        .to_owned();
+
+    let response = app.delete_me(&serde_json::json!({ "password": "password123" })).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Only the confirmation is left of the emails to the user
+    let emails = app.email_outbox
+        .read()
+        .await
+        .get_emails(&Email::parse(&random_email).unwrap())
+        .await
+        .expect("Failed to read the outbox");
+
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].message.subject, "Your account has been deleted");
+
+    let audit_events = app.audit_log_store
+        .read()
+        .await
+        .get_events(&Email::parse(&random_email).unwrap())
+        .await
+        .expect("Failed to read the audit log");
+
+    assert!(audit_events.is_empty());
+}
@@ -0,0 +1,157 @@
+use auth_service::domain::{Email, PasswordResetToken};
+use auth_service::routes::PasswordResetResponse;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    (random_email, cookie.value().to_owned())
+}
+
+// The emailed token can't be read back in tests, so a known token is stored directly
+async fn add_reset_token(app: &TestApp, email: &str) -> PasswordResetToken {
+    let token = PasswordResetToken::default();
+
+    app.password_reset_token_store
+        .write()
+        .await
+        .add_token(token.clone(), Email::parse(email).unwrap())
+        .await
+        .expect("Failed to add password reset token");
+
+    token
+}
+
+#[api_test]
+async fn should_return_the_same_response_for_known_and_unknown_emails() {
+    let app = TestApp::new().await;
+
+    let (email, _) = signup_and_login(&app).await;
+
+    let known = app
+        .post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+
+    assert_eq!(known.status().as_u16(), 200);
+
+    let unknown = app
+        .post_password_reset_request(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(unknown.status().as_u16(), 200);
+
+    let known = known
+        .json::<PasswordResetResponse>()
+        .await
+        .expect("Could not deserialize response body to PasswordResetResponse");
+    let unknown = unknown
+        .json::<PasswordResetResponse>()
+        .await
+        .expect("Could not deserialize response body to PasswordResetResponse");
+
+    assert_eq!(known, unknown);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": "invalid" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let (email, _) = signup_and_login(&app).await;
+    let token = add_reset_token(&app, &email).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token.as_ref(),
+            "newPassword": "short",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_reset_password_and_invalidate_sessions() {
+    let app = TestApp::new().await;
+
+    let (email, jwt) = signup_and_login(&app).await;
+    let token = add_reset_token(&app, &email).await;
+
+    let confirm_body = serde_json::json!({
+        "token": token.as_ref(),
+        "newPassword": "new-password123",
+    });
+
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": jwt })).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "new-password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token is single use
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_token_unknown() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": PasswordResetToken::default().as_ref(),
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
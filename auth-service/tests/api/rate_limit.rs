@@ -0,0 +1,87 @@
+use auth_service::domain::LoginAttemptId;
+use auth_service::utils::constants::RATE_LIMITS;
+use auth_service::utils::rate_limit::{RATE_LIMIT_LIMIT_HEADER, RATE_LIMIT_REMAINING_HEADER, RATE_LIMIT_RESET_HEADER};
+use reqwest::header::RETRY_AFTER;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+fn header_value(response: &reqwest::Response, name: &str) -> u64 {
+    response
+        .headers()
+        .get(name)
+        .unwrap_or_else(|| panic!("No {} header found", name))
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+// Rejected by the handler without touching any store, so only the limiter does any work
+fn invalid_verify_2fa_body() -> serde_json::Value {
+    serde_json::json!({
+        "email": get_random_email(),
+        "loginAttemptId": LoginAttemptId::default(),
+        "2FACode": "1234",
+    })
+}
+
+#[api_test]
+async fn should_return_rate_limit_headers() {
+    let app = TestApp::new().await;
+
+    let capacity = RATE_LIMITS.for_route("/verify-2fa").capacity as u64;
+
+    let response = app.post_verify_2fa(&invalid_verify_2fa_body()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(header_value(&response, RATE_LIMIT_LIMIT_HEADER), capacity);
+    assert_eq!(header_value(&response, RATE_LIMIT_REMAINING_HEADER), capacity - 1);
+    assert!(header_value(&response, RATE_LIMIT_RESET_HEADER) > 0);
+    assert!(!response.headers().contains_key(RETRY_AFTER));
+}
+
+#[api_test]
+async fn should_return_429_if_rate_limit_exceeded() {
+    let app = TestApp::new().await;
+
+    let capacity = RATE_LIMITS.for_route("/verify-2fa").capacity;
+
+    // The bucket slowly refills while the requests are sent, so a few extra ones may get through
+    let mut allowed = 0;
+    let mut limited_response = None;
+
+    for _ in 0..capacity * 2 {
+        let response = app.post_verify_2fa(&invalid_verify_2fa_body()).await;
+
+        if response.status().as_u16() == 429 {
+            limited_response = Some(response);
+            break;
+        }
+
+        assert_eq!(response.status().as_u16(), 400);
+        allowed += 1;
+    }
+
+    assert!(allowed >= capacity);
+
+    let response = limited_response.expect("Rate limit was never reached");
+
+    assert_eq!(header_value(&response, RATE_LIMIT_REMAINING_HEADER), 0);
+    assert!(header_value(&response, RETRY_AFTER.as_str()) > 0);
+
+    // Other routes have buckets of their own
+    let response = app.post_login(&serde_json::json!({ "email": get_random_email() })).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[api_test]
+async fn should_not_rate_limit_verify_token() {
+    let app = TestApp::new().await;
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": "invalid" })).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(!response.headers().contains_key(RATE_LIMIT_LIMIT_HEADER));
+}
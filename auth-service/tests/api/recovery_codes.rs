@@ -0,0 +1,243 @@
+use auth_service::domain::{Email, RECOVERY_CODE_COUNT};
+use auth_service::routes::{RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse};
+use auth_service::utils::auth::MAX_2FA_ATTEMPTS;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+// Signs up a verified user and returns their email along with the recovery codes handed out at signup
+async fn signup(app: &TestApp, requires_2fa: bool) -> (String, Vec<String>) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .unwrap_or_default();
+
+    app.verify_email(&random_email).await;
+
+    (random_email, recovery_codes)
+}
+
+async fn login(app: &TestApp, email: &str) -> String {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn verify_with_recovery_code(app: &TestApp, email: &str, recovery_code: &str) -> reqwest::Response {
+    let login_attempt_id = login(app, email).await;
+
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": recovery_code,
+    }))
+        .await
+}
+
+#[api_test]
+async fn should_return_200_if_recovery_code_used_once() {
+    let app = TestApp::new().await;
+
+    let (email, recovery_codes) = signup(&app, true).await;
+
+    assert_eq!(recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+    // Codes are accepted regardless of case and separator
+    let recovery_code = recovery_codes[0].to_uppercase().replace('-', "");
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_code).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[1]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_invalidate_old_codes_when_regenerated() {
+    let app = TestApp::new().await;
+
+    let (email, old_codes) = signup(&app, true).await;
+
+    let response = verify_with_recovery_code(&app, &email, &old_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+
+    assert_eq!(new_codes.len(), RECOVERY_CODE_COUNT);
+
+    let response = verify_with_recovery_code(&app, &email, &old_codes[1]).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = verify_with_recovery_code(&app, &email, &new_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_401_if_incorrect_password() {
+    let app = TestApp::new().await;
+
+    let (email, recovery_codes) = signup(&app, true).await;
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "wrongpassword" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_400_if_2fa_disabled() {
+    let app = TestApp::new().await;
+
+    let (email, recovery_codes) = signup(&app, false).await;
+
+    assert!(recovery_codes.is_empty());
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_not_deadlock_with_concurrent_login() {
+    let app = TestApp::new().await;
+
+    let (email, recovery_codes) = signup(&app, true).await;
+
+    let login_attempt_id = login(&app, &email).await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let verify_2fa_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": recovery_codes[0],
+    });
+
+    let requests = async { tokio::join!(app.post_login(&login_body), app.post_verify_2fa(&verify_2fa_body)) };
+
+    tokio::time::timeout(std::time::Duration::from_secs(10), requests)
+        .await
+        .expect("Login and 2FA verification deadlocked");
+}
+
+#[api_test]
+async fn should_not_use_up_recovery_code_on_locked_login_attempt() {
+    let app = TestApp::new().await;
+
+    let (email, recovery_codes) = signup(&app, true).await;
+
+    let login_attempt_id = login(&app, &email).await;
+
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(&email).unwrap())
+        .await
+        .unwrap();
+
+    let wrong_code = if two_fa_code.as_ref() == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..MAX_2FA_ATTEMPTS {
+        app.post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": wrong_code,
+        }))
+            .await;
+    }
+
+    let response = app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": recovery_codes[0],
+    }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    // The code still works for the next login
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,151 @@
+use reqwest::Url;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("Refresh cookie not found");
+
+    assert!(!cookie.value().is_empty());
+
+    cookie.value().to_owned()
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[api_test]
+async fn should_return_200_and_rotate_tokens_if_valid_refresh_cookie() {
+    let app = TestApp::new().await;
+
+    let refresh_token = signup_and_login(&app).await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    let new_refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("Refresh cookie not found");
+
+    assert_ne!(new_refresh_cookie.value(), refresh_token);
+
+    let verify_token_body = serde_json::json!({
+        "token": auth_cookie.value(),
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let app = TestApp::new().await;
+
+    set_refresh_cookie(&app, "invalid");
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_revoke_token_family_if_refresh_token_reused() {
+    let app = TestApp::new().await;
+
+    let old_refresh_token = signup_and_login(&app).await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("Refresh cookie not found")
+        .value()
+        .to_owned();
+
+    // Replaying the already exchanged token revokes the whole family...
+    set_refresh_cookie(&app, &old_refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // ...including the token issued by the legitimate rotation
+    set_refresh_cookie(&app, &new_refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_after_logout() {
+    let app = TestApp::new().await;
+
+    let refresh_token = signup_and_login(&app).await;
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,193 @@
+use auth_service::domain::{Email, LoginAttemptId};
+use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::utils::auth::MAX_2FA_RESENDS;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+// Signs up a user with email 2FA and starts a login, returning the email and the login attempt id
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    (random_email, login_attempt_id)
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({
+            "email": "invalid_email.com",
+            "loginAttemptId": LoginAttemptId::default(),
+        }),
+        serde_json::json!({
+            "email": get_random_email(),
+            "loginAttemptId": "invalid",
+        }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_resend_2fa(test_case).await;
+
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
+
+#[api_test]
+async fn should_return_401_if_login_attempt_id_incorrect() {
+    let app = TestApp::new().await;
+
+    let (email, _) = signup_and_login(&app).await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": LoginAttemptId::default(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_replace_code_if_valid_input() {
+    let app = TestApp::new().await;
+
+    let (email, login_attempt_id) = signup_and_login(&app).await;
+
+    let (_, old_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(&email).unwrap())
+        .await
+        .unwrap();
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let (stored_login_attempt_id, new_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(&email).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(stored_login_attempt_id, LoginAttemptId::parse(login_attempt_id.clone()).unwrap());
+
+    // Codes are random, so the new one only differs most of the time
+    if new_code != old_code {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": old_code,
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": new_code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_429_if_resent_too_often() {
+    let app = TestApp::new().await;
+
+    let (email, login_attempt_id) = signup_and_login(&app).await;
+
+    let resend_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+    });
+
+    for _ in 0..MAX_2FA_RESENDS {
+        let response = app.post_resend_2fa(&resend_body).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app.post_resend_2fa(&resend_body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Logging in again starts a new attempt
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
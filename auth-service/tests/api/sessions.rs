@@ -0,0 +1,133 @@
+use auth_service::routes::SessionsResponse;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    random_email
+}
+
+async fn login(app: &TestApp, email: &str) -> String {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    cookie.value().to_owned()
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_session("session").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_current_session() {
+    let app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    login(&app, &email).await;
+
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse");
+
+    assert_eq!(json_body.sessions.len(), 1);
+    assert!(json_body.sessions[0].current);
+}
+
+#[api_test]
+async fn should_revoke_another_session() {
+    let app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let first_token = login(&app, &email).await;
+    login(&app, &email).await;
+
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse");
+
+    assert_eq!(json_body.sessions.len(), 2);
+
+    let other_session = json_body
+        .sessions
+        .iter()
+        .find(|session| !session.current)
+        .expect("Other session not found");
+
+    let response = app.delete_session(&other_session.id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = app
+        .get_sessions()
+        .await
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse");
+
+    assert_eq!(json_body.sessions.len(), 1);
+
+    let verify_token_body = serde_json::json!({
+        "token": first_token,
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_404_if_session_not_found() {
+    let app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    login(&app, &email).await;
+
+    let response = app.delete_session("unknown").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -1,7 +1,8 @@
 use test_helpers::api_test;
 use crate::helpers::{get_random_email, TestApp};
 
-use auth_service::{routes::SignupResponse, ErrorResponse};
+use auth_service::{domain::RECOVERY_CODE_COUNT, routes::SignupResponse, ErrorResponse};
+use auth_service::domain::{Email, EmailVerificationToken, UserStoreError};
 
 #[api_test]
 async fn should_return_422_if_malformed_input() {
@@ -52,18 +53,16 @@ async fn should_return_201_if_valid_input() {
     
     assert_eq!(response.status().as_u16(), 201);
 
-    let expected_response = SignupResponse {
-        message: "User created successfully!".to_owned(),
-    };
+    let json_body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to UserBody");
 
     // Assert that we are getting the correct response body!
-    assert_eq!(
-        response
-            .json::<SignupResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        expected_response
-    );
+    assert_eq!(json_body.message, "User created successfully!".to_owned());
+
+    // Users with 2FA get their recovery codes once, right here
+    assert_eq!(json_body.recovery_codes.map(|codes| codes.len()), Some(RECOVERY_CODE_COUNT));
 }
 
 #[api_test]
@@ -145,3 +144,35 @@ async fn should_return_409_if_email_already_exists() {
         "User already exists".to_owned()
     );
 }
+
+#[api_test]
+async fn should_remove_user_if_verification_email_fails() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let email = Email::parse(&random_email).unwrap();
+
+    // A token issued just now puts the email on cooldown, so the verification email can't be sent
+    app.email_verification_token_store
+        .write()
+        .await
+        .add_token(EmailVerificationToken::default(), email.clone())
+        .await
+        .unwrap();
+
+    let user_data = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&user_data).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Nothing is left behind that would make a retry fail with 409
+    assert_eq!(
+        app.user_store.read().await.get_user(&email).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use auth_service::domain::{Email, EmailClient, EmailClientError, EmailMessage};
+use auth_service::{SmtpConfig, SmtpEmailClient, SmtpTls};
+use crate::helpers::{get_random_email, FakeSmtpBehaviour, FakeSmtpServer};
+
+fn smtp_config(port: u16) -> SmtpConfig {
+    SmtpConfig {
+        host: "127.0.0.1".to_owned(),
+        port,
+        tls: SmtpTls::None,
+        username: Some("smtp-user".to_owned()),
+        password: Some("smtp-password".to_owned()),
+        from: "Auth Service <no-reply@example.com>".to_owned(),
+        timeout: Duration::from_secs(1),
+    }
+}
+
+fn message() -> EmailMessage {
+    EmailMessage {
+        subject: "Your 2FA Code".to_owned(),
+        html: "<p>123456</p>".to_owned(),
+        text: "123456".to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn should_send_email_to_smtp_server() {
+    let server = FakeSmtpServer::start(FakeSmtpBehaviour::Accept).await;
+    let client = SmtpEmailClient::new(&smtp_config(server.port)).unwrap();
+
+    let recipient = get_random_email();
+
+    let result = client
+        .send_email(&Email::parse(&recipient).unwrap(), &message())
+        .await;
+
+    assert_eq!(result, Ok(()));
+
+    let emails = server.emails.lock().await;
+
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].from, "no-reply@example.com");
+    assert_eq!(emails[0].recipients, vec![recipient]);
+    assert_eq!(emails[0].credentials.as_deref(), Some("smtp-user:smtp-password"));
+    assert!(emails[0].data.contains("Subject: Your 2FA Code"));
+    assert!(emails[0].data.contains("multipart/alternative"));
+    assert!(emails[0].data.contains("<p>123456</p>"));
+}
+
+#[tokio::test]
+async fn should_return_rejected_if_recipient_refused() {
+    let server = FakeSmtpServer::start(FakeSmtpBehaviour::RejectRecipients).await;
+    let client = SmtpEmailClient::new(&smtp_config(server.port)).unwrap();
+
+    let result = client
+        .send_email(&Email::parse(&get_random_email()).unwrap(), &message())
+        .await;
+
+    assert!(matches!(result, Err(EmailClientError::Rejected(_))));
+    assert!(!result.unwrap_err().is_retryable());
+    assert!(server.emails.lock().await.is_empty());
+}
+
+#[tokio::test]
+async fn should_return_unavailable_if_server_times_out() {
+    let server = FakeSmtpServer::start(FakeSmtpBehaviour::Stall).await;
+    let client = SmtpEmailClient::new(&smtp_config(server.port)).unwrap();
+
+    let result = tokio::time::timeout(
+        Duration::from_secs(10),
+        client.send_email(&Email::parse(&get_random_email()).unwrap(), &message()),
+    )
+        .await
+        .expect("The client didn't time out on its own");
+
+    assert!(matches!(result, Err(EmailClientError::Unavailable(_))));
+    assert!(result.unwrap_err().is_retryable());
+}
+
+#[tokio::test]
+async fn should_return_unavailable_if_server_unreachable() {
+    // Nothing listens on the port once the listener is dropped
+    let port = {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    };
+
+    let client = SmtpEmailClient::new(&smtp_config(port)).unwrap();
+
+    let result = client
+        .send_email(&Email::parse(&get_random_email()).unwrap(), &message())
+        .await;
+
+    assert!(matches!(result, Err(EmailClientError::Unavailable(_))));
+}
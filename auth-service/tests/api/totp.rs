@@ -0,0 +1,211 @@
+use auth_service::domain::{Email, RECOVERY_CODE_COUNT};
+use auth_service::routes::{RecoveryCodesResponse, TotpEnrollmentResponse, TwoFactorAuthResponse};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::utils::totp;
+use chrono::Utc;
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    random_email
+}
+
+async fn enroll(app: &TestApp) -> TotpEnrollmentResponse {
+    let response = app.post_totp_enroll().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse")
+}
+
+fn current_code(secret: &str) -> String {
+    totp::generate_code(secret, Utc::now().timestamp())
+        .expect("Invalid TOTP secret")
+        .as_ref()
+        .to_owned()
+}
+
+// A code from an hour ago is outside any reasonable skew window
+fn stale_code(secret: &str) -> String {
+    totp::generate_code(secret, Utc::now().timestamp() - 3600)
+        .expect("Invalid TOTP secret")
+        .as_ref()
+        .to_owned()
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.post_totp_enroll().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_totp_confirm(&serde_json::json!({ "code": "123456", "password": "password123" })).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_otpauth_uri_on_enrollment() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+
+    let enrollment = enroll(&app).await;
+
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment.otpauth_uri.contains(&format!("secret={}", enrollment.secret)));
+
+    // 2FA stays off until the enrollment is confirmed
+    let user = app.user_store.read().await.get_user(&Email::parse(&email).unwrap()).await.unwrap();
+
+    assert!(!user.requires_2fa);
+}
+
+#[api_test]
+async fn should_return_401_if_confirmation_code_incorrect() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let enrollment = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": stale_code(&enrollment.secret), "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_confirmation_password_incorrect() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+
+    let enrollment = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": current_code(&enrollment.secret), "password": "wrongpassword" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Nothing changes without the password
+    let user = app.user_store.read().await.get_user(&Email::parse(&email).unwrap()).await.unwrap();
+
+    assert!(!user.requires_2fa);
+}
+
+#[api_test]
+async fn should_return_400_if_confirmed_without_enrollment() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app.post_totp_confirm(&serde_json::json!({ "code": "123456", "password": "password123" })).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_require_totp_code_on_login_once_confirmed() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+
+    let enrollment = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": current_code(&enrollment.secret), "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let recovery_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse");
+
+    assert_eq!(recovery_codes.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+    // A second enrollment would replace the secret the app is using
+    assert_eq!(app.post_totp_enroll().await.status().as_u16(), 409);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    // The code kept in the store is never emailed, so it must not be accepted either
+    let (_, stored_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(&email).unwrap())
+        .await
+        .unwrap();
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": stored_code.as_ref(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": current_code(&enrollment.secret),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+}
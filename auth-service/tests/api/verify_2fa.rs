@@ -1,7 +1,9 @@
 use test_helpers::api_test;
 use auth_service::domain::{Email, LoginAttemptId, TwoFACode};
 use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::utils::auth::MAX_2FA_ATTEMPTS;
 use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::ErrorResponse;
 use crate::helpers::{get_random_email, TestApp};
 
 #[api_test]
@@ -71,6 +73,8 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123"
@@ -139,6 +143,8 @@ async fn should_return_200_if_correct_code() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123"
@@ -184,4 +190,162 @@ async fn should_return_200_if_correct_code() {
         .expect("Auth cookie not found");
 
     assert!(!cookie.value().is_empty());
-}
\ No newline at end of file
+}
+#[api_test]
+async fn should_return_429_after_too_many_failed_attempts() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let (login_attempt_id, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(&random_email).unwrap())
+        .await
+        .unwrap();
+
+    let wrong_code = if two_fa_code.as_ref() == "000000" { "111111" } else { "000000" };
+
+    let wrong_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": wrong_code
+    });
+
+    for _ in 1..MAX_2FA_ATTEMPTS {
+        let response = app.post_verify_2fa(&wrong_body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_verify_2fa(&wrong_body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many failed 2FA attempts, log in again".to_owned()
+    );
+
+    // The code is dead even though it is the right one
+    let correct_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code
+    });
+
+    let response = app.post_verify_2fa(&correct_body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    // Logging in again issues a new code with a fresh set of attempts
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let (login_attempt_id, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(&random_email).unwrap())
+        .await
+        .unwrap();
+
+    let two_fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code
+    });
+
+    let response = app.post_verify_2fa(&two_fa_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_not_count_attempts_with_another_login_attempt_id() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let foreign_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": LoginAttemptId::default(),
+        "2FACode": "000000"
+    });
+
+    for _ in 0..MAX_2FA_ATTEMPTS {
+        let response = app.post_verify_2fa(&foreign_body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let (login_attempt_id, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(&random_email).unwrap())
+        .await
+        .unwrap();
+
+    let two_fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code
+    });
+
+    let response = app.post_verify_2fa(&two_fa_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,103 @@
+use auth_service::domain::{Email, EmailVerificationToken, Password, User};
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+// Adds an unverified user along with a known verification token, as the emailed one can't be read back
+async fn add_unverified_user(app: &TestApp) -> (String, EmailVerificationToken) {
+    let random_email = get_random_email();
+    let email = Email::parse(&random_email).unwrap();
+
+    let user = User::new(email.clone(), Password::parse("password123").unwrap(), false);
+
+    app.user_store
+        .write()
+        .await
+        .add_user(user)
+        .await
+        .expect("Failed to add user");
+
+    let token = EmailVerificationToken::default();
+
+    app.email_verification_token_store
+        .write()
+        .await
+        .add_token(token.clone(), email)
+        .await
+        .expect("Failed to add email verification token");
+
+    (random_email, token)
+}
+
+#[api_test]
+async fn should_verify_email_and_allow_login() {
+    let app = TestApp::new().await;
+
+    let (email, token) = add_unverified_user(&app).await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.get_verify_email(token.as_ref()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token is single use
+    let response = app.get_verify_email(token.as_ref()).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_token_invalid() {
+    let app = TestApp::new().await;
+
+    let response = app.get_verify_email("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_verify_email(EmailVerificationToken::default().as_ref()).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_rate_limit_resend() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Signup has just sent the first link
+    let response = app
+        .post_resend_verification_email(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Unknown emails are not revealed
+    let response = app
+        .post_resend_verification_email(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -1,5 +1,7 @@
 use test_helpers::api_test;
-use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::domain::{AccountStatus, Email};
+use auth_service::utils::auth::Claims;
+use auth_service::utils::constants::{JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER};
 use crate::helpers::{get_random_email, TestApp};
 
 #[api_test]
@@ -46,6 +48,50 @@ async fn should_return_200_valid_token() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    let verify_token_body = serde_json::json!({
+        "token": cookie.value(),
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status(), 200);
+}
+
+#[api_test]
+async fn should_return_claims_if_requested() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -62,11 +108,21 @@ async fn should_return_200_valid_token() {
 
     let verify_token_body = serde_json::json!({
         "token": cookie.value(),
+        "includeClaims": true,
     });
 
     let response = app.post_verify_token(&verify_token_body).await;
 
     assert_eq!(response.status(), 200);
+
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+
+    assert_eq!(claims.sub, random_email);
+    assert_eq!(claims.iss, *JWT_ISSUER);
+    assert_eq!(claims.aud, *JWT_AUDIENCE);
 }
 
 #[api_test]
@@ -100,6 +156,8 @@ async fn should_return_401_if_banned_token() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -128,3 +186,57 @@ async fn should_return_401_if_banned_token() {
 
     assert_eq!(response.status(), 401);
 }
+
+#[api_test]
+async fn should_return_401_if_account_suspended() {
+
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Auth cookie not found");
+
+    // Suspended directly in the store, so the token isn't revoked any other way
+    app.user_store
+        .write()
+        .await
+        .set_status(&Email::parse(&random_email).unwrap(), AccountStatus::Suspended)
+        .await
+        .expect("Failed to suspend the user");
+
+    let verify_token_body = serde_json::json!({
+        "token": cookie.value(),
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
@@ -0,0 +1,305 @@
+use auth_service::domain::Email;
+use auth_service::routes::{LoginOptionsResponse, RegistrationOptionsResponse};
+use auth_service::utils::constants::{JWT_COOKIE_NAME, WEBAUTHN_RELYING_PARTY};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value;
+use ring::digest::{digest, SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use crate::helpers::{get_random_email, TestApp};
+
+use test_helpers::api_test;
+
+// Ed25519 authenticator answering the ceremonies the way a browser would relay them
+struct SoftAuthenticator {
+    key_pair: Ed25519KeyPair,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+}
+
+impl SoftAuthenticator {
+    fn new() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+
+        SoftAuthenticator {
+            key_pair: Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap(),
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            sign_count: 0,
+        }
+    }
+
+    fn id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data(ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        serde_json::json!({
+            "type": ceremony_type,
+            "challenge": challenge,
+            "origin": WEBAUTHN_RELYING_PARTY.origin,
+        })
+            .to_string()
+            .into_bytes()
+    }
+
+    fn authenticator_data(&self, flags: u8) -> Vec<u8> {
+        let mut data = digest(&SHA256, WEBAUTHN_RELYING_PARTY.id.as_bytes()).as_ref().to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    fn register(&self, challenge: &str) -> serde_json::Value {
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(1)),
+            (Value::from(3), Value::from(-8)),
+            (Value::from(-1), Value::from(6)),
+            (Value::from(-2), Value::Bytes(self.key_pair.public_key().as_ref().to_vec())),
+        ]);
+
+        // User present, user verified and attested credential data included
+        let mut authenticator_data = self.authenticator_data(0x45);
+        authenticator_data.extend_from_slice(&[0; 16]);
+        authenticator_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        authenticator_data.extend_from_slice(&self.credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut authenticator_data).unwrap();
+
+        let attestation_object = Value::Map(vec![
+            (Value::Text("fmt".to_owned()), Value::Text("none".to_owned())),
+            (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+            (Value::Text("authData".to_owned()), Value::Bytes(authenticator_data)),
+        ]);
+        let mut encoded_attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation_object, &mut encoded_attestation_object).unwrap();
+
+        serde_json::json!({
+            "id": self.id(),
+            "rawId": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(Self::client_data("webauthn.create", challenge)),
+                "attestationObject": URL_SAFE_NO_PAD.encode(encoded_attestation_object),
+            },
+        })
+    }
+
+    fn login(&mut self, challenge: &str) -> serde_json::Value {
+        self.sign_count += 1;
+
+        let client_data = Self::client_data("webauthn.get", challenge);
+        let authenticator_data = self.authenticator_data(0x05);
+
+        let mut signed_data = authenticator_data.clone();
+        signed_data.extend_from_slice(digest(&SHA256, &client_data).as_ref());
+
+        serde_json::json!({
+            "id": self.id(),
+            "rawId": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(authenticator_data),
+                "signature": URL_SAFE_NO_PAD.encode(self.key_pair.sign(&signed_data)),
+            },
+        })
+    }
+}
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    random_email
+}
+
+async fn register(app: &TestApp, authenticator: &SoftAuthenticator) {
+    let response = app.post_webauthn_register_begin().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let options = response
+        .json::<RegistrationOptionsResponse>()
+        .await
+        .expect("Could not deserialize response body to RegistrationOptionsResponse");
+
+    let response = app
+        .post_webauthn_register_finish(&authenticator.register(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn begin_login(app: &TestApp, email: Option<&str>) -> LoginOptionsResponse {
+    let response = app
+        .post_webauthn_login_begin(&serde_json::json!({ "email": email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<LoginOptionsResponse>()
+        .await
+        .expect("Could not deserialize response body to LoginOptionsResponse")
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.post_webauthn_register_begin().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_register_and_login_with_passkey() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+
+    let mut authenticator = SoftAuthenticator::new();
+
+    register(&app, &authenticator).await;
+
+    let credentials = app
+        .credential_store
+        .read()
+        .await
+        .get_credentials(&Email::parse(&email).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(credentials.len(), 1);
+    assert_eq!(credentials[0].id, authenticator.id());
+
+    let options = begin_login(&app, Some(&email)).await;
+
+    assert_eq!(options.public_key.rp_id, WEBAUTHN_RELYING_PARTY.id);
+    assert_eq!(options.public_key.allow_credentials.len(), 1);
+    assert_eq!(options.public_key.allow_credentials[0].id, authenticator.id());
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.login(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    // Discoverable credentials work without naming the account
+    let options = begin_login(&app, None).await;
+
+    assert!(options.public_key.allow_credentials.is_empty());
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.login(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_challenge_reused() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+
+    let mut authenticator = SoftAuthenticator::new();
+
+    register(&app, &authenticator).await;
+
+    let options = begin_login(&app, Some(&email)).await;
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.login(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.login(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_signed_by_another_key() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+
+    let authenticator = SoftAuthenticator::new();
+
+    register(&app, &authenticator).await;
+
+    // Same credential id, different private key
+    let mut impostor = SoftAuthenticator::new();
+    impostor.credential_id = authenticator.credential_id.clone();
+
+    let options = begin_login(&app, Some(&email)).await;
+
+    let response = app
+        .post_webauthn_login_finish(&impostor.login(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_credential_unknown() {
+    let app = TestApp::new().await;
+
+    let options = begin_login(&app, None).await;
+
+    let response = app
+        .post_webauthn_login_finish(&SoftAuthenticator::new().login(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_passkey_belongs_to_another_account() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let mut authenticator = SoftAuthenticator::new();
+
+    register(&app, &authenticator).await;
+
+    let other_email = signup_and_login(&app).await;
+
+    let options = begin_login(&app, Some(&other_email)).await;
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.login(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}